@@ -0,0 +1,103 @@
+//! Compares the indexed navigation of `QueryResult` with re-querying the
+//! whole root and filtering by path, which is what it used to do, and
+//! `query_iter` with `query`.
+//!
+//! Run with `cargo bench`.
+
+extern crate syn;
+extern crate syn_query;
+
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+use syn::{ExprCall, File, Local, Stmt};
+use syn_query::Queryable;
+
+fn source(functions: usize) -> String {
+    let mut source = String::new();
+    for i in 0..functions {
+        source.push_str(&format!(
+            "fn f{i}(a: u32, b: u32) -> u32 {{\n    let c = g(a, b);\n    let d = h(c, [a, b, c]);\n    if d > 0 {{ k(d) }} else {{ m(c, d) }}\n}}\n",
+            i = i
+        ));
+    }
+    source
+}
+
+fn time<F: FnMut() -> usize>(name: &str, runs: u32, mut f: F) -> Duration {
+    let start = Instant::now();
+    let mut len = 0;
+    for _ in 0..runs {
+        len = f();
+    }
+    let elapsed = start.elapsed() / runs;
+    println!("{:<28} {:>12?} ({} nodes)", name, elapsed, len);
+    elapsed
+}
+
+fn filtered(file: &File, paths: &BTreeSet<Vec<i64>>) -> usize {
+    file.query::<Stmt>()
+        .filter(|node| paths.contains(&node.path))
+        .len()
+}
+
+fn main() {
+    let file: File = syn::parse_file(&source(400)).unwrap();
+
+    let start = Instant::now();
+    let calls = file.query::<ExprCall>();
+    let built = calls.document().len();
+    println!(
+        "document of {} nodes built in {:?}\n",
+        built,
+        start.elapsed()
+    );
+
+    let rescan = time("parents (rescan)", 5, || {
+        let mut paths = BTreeSet::new();
+        for call in calls.iter() {
+            for len in 0..call.path.len() {
+                paths.insert(call.path[..len].to_vec());
+            }
+        }
+        filtered(&file, &paths)
+    });
+    let indexed = time("parents (indexed)", 5, || calls.parents::<Stmt>().len());
+    println!(
+        "speedup {:.1}x\n",
+        rescan.as_secs_f64() / indexed.as_secs_f64()
+    );
+
+    let locals = calls.parents::<Local>();
+    let rescan = time("next statement (rescan)", 5, || {
+        let parents: BTreeSet<Vec<i64>> = locals
+            .iter()
+            .map(|node| node.path[..node.path.len() - 1].to_vec())
+            .collect();
+        let next: BTreeSet<Vec<i64>> = file
+            .query::<Stmt>()
+            .filter(|node| parents.contains(&node.path))
+            .iter()
+            .map(|node| {
+                let mut path = node.path.to_owned();
+                if let Some(last) = path.last_mut() {
+                    *last += 1;
+                }
+                path
+            })
+            .collect();
+        filtered(&file, &next)
+    });
+    let indexed = time("next statement (indexed)", 5, || {
+        locals.parent::<Stmt>().next::<Stmt>().len()
+    });
+    println!(
+        "speedup {:.1}x\n",
+        rescan.as_secs_f64() / indexed.as_secs_f64()
+    );
+
+    let eager = time("calls (query)", 5, || file.query::<ExprCall>().len());
+    let lazy = time("calls (query_iter)", 5, || {
+        file.query_iter::<ExprCall>().count()
+    });
+    println!("ratio {:.1}x", lazy.as_secs_f64() / eager.as_secs_f64());
+}
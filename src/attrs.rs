@@ -0,0 +1,62 @@
+use syn::{Attribute, Lit, Meta, NestedMeta, Path};
+use {DynNode, Node, Queryable};
+
+/// Path segment between an `Attribute` and the `Meta` parsed from it,
+/// which is at the attribute's path followed by `ATTR_META`.
+pub const ATTR_META: i64 = -2;
+
+/// The `U` children of `node`, or those of the node it wraps, as an `Item`
+/// wraps an `ItemStruct`: its attributes for `Attribute`.
+pub(crate) fn own<U: Queryable, T: Queryable>(node: &T, path: &[i64]) -> Vec<Node<U>> {
+    let mut children = node.visit::<DynNode>(path.to_owned(), Some(1));
+    children.retain(|child| child.path.len() > path.len());
+    let own: Vec<Node<U>> = children
+        .iter()
+        .filter_map(|child| child.downcast())
+        .collect();
+    match children.len() {
+        1 if own.is_empty() => self::own(&children[0].data, &children[0].path),
+        _ => own,
+    }
+}
+
+/// `a::b` for the path of `#[a::b]`.
+pub(crate) fn name(path: &Path) -> String {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    segments.join("::")
+}
+
+/// The `Meta` of an attribute, below it at `ATTR_META` and at its
+/// location. Its own nodes continue that path, they are not part of the
+/// document.
+pub(crate) fn meta(attr: &Node<Attribute>) -> Option<Node<Meta>> {
+    attr.data.interpret_meta().map(|data| {
+        let mut path = attr.path.to_owned();
+        path.push(ATTR_META);
+        Node {
+            data,
+            path,
+            location: attr.location,
+        }
+    })
+}
+
+/// The literals of `key = ...` in the list of `meta`.
+pub(crate) fn values(meta: &Node<Meta>, key: &str) -> Vec<Node<Lit>> {
+    meta.data
+        .visit::<NestedMeta>(meta.path.to_owned(), Some(2))
+        .into_iter()
+        .filter_map(|node| match node.data {
+            NestedMeta::Meta(Meta::NameValue(ref pair)) if pair.ident == key => node
+                .data
+                .visit::<Lit>(node.path.to_owned(), None)
+                .into_iter()
+                .next(),
+            _ => None,
+        })
+        .collect()
+}
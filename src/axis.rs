@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+/// Siblings kept by `prev_until` and `next_until`, bounded within each
+/// parent by the stops found, shared by the owned and borrowed query
+/// results.
+pub(crate) enum Axis {
+    Before(HashMap<Vec<i64>, i64>),
+    After(HashMap<Vec<i64>, i64>),
+}
+
+fn bounds<'a, I>(paths: I, keep_max: bool) -> HashMap<Vec<i64>, i64>
+where
+    I: IntoIterator<Item = &'a Vec<i64>>,
+{
+    let mut map = HashMap::new();
+    for path in paths {
+        let mut path = path.to_owned();
+        if let Some(last) = path.pop() {
+            if map
+                .get(&path)
+                .is_none_or(|value| (*value < last) == keep_max)
+            {
+                map.insert(path, last);
+            }
+        }
+    }
+    map
+}
+
+impl Axis {
+    /// Keeps the nodes that come after the first of `stops`.
+    pub(crate) fn prev_until<'a, I>(stops: I) -> Axis
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        Axis::After(bounds(stops, false))
+    }
+    /// Keeps the nodes that come before the last of `stops`.
+    pub(crate) fn next_until<'a, I>(stops: I) -> Axis
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        Axis::Before(bounds(stops, true))
+    }
+    pub(crate) fn contains(&self, path: &[i64]) -> bool {
+        let split = path.split_last();
+        match *self {
+            Axis::Before(ref bounds) => split.is_some_and(|(last, parent)| {
+                bounds.get(parent).is_none_or(|value| *last < *value)
+            }),
+            Axis::After(ref bounds) => split.is_some_and(|(last, parent)| {
+                bounds.get(parent).is_none_or(|value| *last > *value)
+            }),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! `synq QUERY [PATH]...` prints the nodes of Rust source files matching a
+//! selector, in the manner of grep.
+
+extern crate syn;
+extern crate syn_query;
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use syn_query::{json_string, Location, Queryable, Selector};
+
+const USAGE: &str = "usage: synq [OPTIONS] QUERY [PATH]...
+
+Print the nodes of Rust source files matching QUERY, a selector such as
+`ItemFn > Block ExprMethodCall` or a single node kind such as `ItemStruct`.
+Directories are searched recursively for `.rs` files, skipping `target`,
+hidden directories and links to directories; the default PATH is the
+current directory. Nodes without a place in the source, such as the
+omitted return type of `fn f() {}`, are printed with `?` for their line
+and column, followed by their code or, when they have none, their kind.
+
+options:
+    -c, --count    print the number of matches of each file
+        --json     print one JSON object per line
+    -q, --quiet    print nothing, only set the exit status
+    -h, --help     print this message
+
+The exit status is 0 if a node matched, 1 if none did and 2 on error.";
+
+#[derive(Default)]
+struct Options {
+    count: bool,
+    json: bool,
+    quiet: bool,
+    query: Option<String>,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut positional = false;
+    for arg in env::args().skip(1) {
+        if positional || !arg.starts_with('-') || arg == "-" {
+            if options.query.is_none() {
+                options.query = Some(arg);
+            } else {
+                options.paths.push(PathBuf::from(arg));
+            }
+            continue;
+        }
+        match arg.as_str() {
+            "--" => positional = true,
+            "-c" | "--count" => options.count = true,
+            "--json" => options.json = true,
+            "-q" | "--quiet" => options.quiet = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => return Err(format!("unknown option `{}`", arg)),
+        }
+    }
+    if options.query.is_none() {
+        return Err("missing QUERY".to_owned());
+    }
+    if options.paths.is_empty() {
+        options.paths.push(PathBuf::from("."));
+    }
+    Ok(options)
+}
+
+/// Files under `path` in sorted order; a file named explicitly is searched
+/// whatever its extension.
+fn walk(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_owned());
+        return Ok(());
+    }
+    walk_dir(path, files)
+}
+
+/// The `.rs` files under `dir`, leaving out `target`, hidden directories
+/// and links to directories, which could lead back to `dir`.
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.path());
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            let name = entry.file_name();
+            let skipped = name == "target" || name.to_string_lossy().starts_with('.');
+            if !skipped {
+                walk_dir(&path, files)?;
+            }
+        } else if path.extension().is_some_and(|extension| extension == "rs") && path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+struct Hit {
+    kind: &'static str,
+    location: Option<Location>,
+    text: String,
+}
+
+fn search(selector: &Selector, source: &str) -> Result<Vec<Hit>, String> {
+    let file = syn::parse_file(source).map_err(|error| error.to_string())?;
+    let root = file.query::<syn::File>();
+    let mut hits = Vec::new();
+    for node in selector.apply(&root) {
+        let text = match node
+            .location
+            .and_then(|location| location.byte_range(source))
+        {
+            Some(range) => source[range].to_owned(),
+            None => node.to_source(),
+        };
+        hits.push(Hit {
+            kind: node.kind().name(),
+            location: node.location,
+            text,
+        });
+    }
+    Ok(hits)
+}
+
+fn print(options: &Options, name: &str, source: &str, hits: &[Hit]) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if options.count {
+        if options.json {
+            writeln!(
+                out,
+                "{{\"file\":{},\"count\":{}}}",
+                json_string(name),
+                hits.len()
+            )
+        } else {
+            writeln!(out, "{}:{}", name, hits.len())
+        }
+    } else {
+        for hit in hits {
+            if options.json {
+                let position = match hit.location {
+                    Some(location) => format!(
+                        "\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}",
+                        location.start.line,
+                        location.start.column + 1,
+                        location.end.line,
+                        location.end.column + 1
+                    ),
+                    None => "\"line\":null,\"column\":null,\"end_line\":null,\"end_column\":null"
+                        .to_owned(),
+                };
+                writeln!(
+                    out,
+                    "{{\"file\":{},\"kind\":{},{},\"text\":{}}}",
+                    json_string(name),
+                    json_string(hit.kind),
+                    position,
+                    json_string(&hit.text)
+                )?;
+            } else {
+                match hit.location {
+                    Some(location) => {
+                        let line = location.start.line;
+                        let snippet = source.lines().nth(line - 1).unwrap_or("").trim();
+                        let column = location.start.column + 1;
+                        writeln!(out, "{}:{}:{}: {}", name, line, column, snippet)?;
+                    }
+                    None if hit.text.is_empty() => writeln!(out, "{}:?:?: {}", name, hit.kind)?,
+                    None => writeln!(out, "{}:?:?: {}", name, hit.text)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("synq: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    let query = options.query.as_ref().map_or("", |query| query.as_str());
+    let selector = match Selector::parse(query) {
+        Ok(selector) => selector,
+        Err(error) => {
+            eprintln!("synq: invalid query `{}`: {}", query, error);
+            process::exit(2);
+        }
+    };
+    let mut files = Vec::new();
+    let mut failed = false;
+    for path in &options.paths {
+        if let Err(error) = walk(path, &mut files) {
+            eprintln!("synq: {}: {}", path.display(), error);
+            failed = true;
+        }
+    }
+    let mut matched = false;
+    for file in files {
+        let name = file.display().to_string();
+        let result = fs::read_to_string(&file)
+            .map_err(|error| error.to_string())
+            .and_then(|source| search(&selector, &source).map(|hits| (source, hits)));
+        let (source, hits) = match result {
+            Ok(result) => result,
+            Err(message) => {
+                eprintln!("synq: {}: {}", name, message);
+                failed = true;
+                continue;
+            }
+        };
+        matched |= !hits.is_empty();
+        if options.quiet {
+            if matched {
+                process::exit(0);
+            }
+            continue;
+        }
+        if let Err(error) = print(&options, &name, &source, &hits) {
+            if error.kind() == io::ErrorKind::BrokenPipe {
+                process::exit(if matched { 0 } else { 1 });
+            }
+            eprintln!("synq: {}", error);
+            process::exit(2);
+        }
+    }
+    process::exit(match (matched, failed) {
+        (_, true) => 2,
+        (true, false) => 0,
+        (false, false) => 1,
+    });
+}
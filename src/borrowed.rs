@@ -0,0 +1,382 @@
+//! Queries that borrow from the syntax tree instead of cloning it.
+//!
+//! `Queryable::query_ref` and friends return a `QueryResult<'ast, T, R>`
+//! holding `&'ast T` references into the root, so neither the matches nor
+//! the root are cloned. Navigation goes through the same `Document` as the
+//! owned results, built once per root, and the nodes reached are then
+//! fetched from the root in one walk. The node type must be a syntax type:
+//! `DynNode` only exists as an owned copy and never matches a borrowed
+//! query.
+//!
+//! ```rust
+//! extern crate syn;
+//! extern crate syn_query;
+//! use syn_query::Queryable;
+//! use syn::{ExprStruct, Ident, Member};
+//! fn main() {
+//!     let st: ExprStruct = syn::parse_str("Point { x: 1, y: 1 }").unwrap();
+//!     let members = st.query_ref::<Member>();
+//!     assert_eq!(members.len(), 2);
+//!     let idents = members.children::<Ident>();
+//!     assert_eq!(*idents[1].data, "y");
+//!     assert_eq!(idents[1].path, vec![3i64, 0, 0]);
+//! }
+//! ```
+
+use axis::Axis;
+use error;
+use proc_macro2::TokenStream;
+use set;
+use std::any::Any;
+use std::cell::OnceCell;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::ops::Index as OpsIndex;
+use std::ptr;
+use std::rc::Rc;
+use syn::Ident;
+use {
+    fetch, is, join_path, Collect, Document, Location, NodeKind, Queryable, Render, RootMismatch,
+};
+
+#[derive(Debug, Clone)]
+pub struct Node<'ast, T: 'ast> {
+    pub data: &'ast T,
+    pub path: Vec<i64>,
+}
+
+impl<'ast, T> PartialEq for Node<'ast, T> {
+    fn eq(&self, other: &Node<'ast, T>) -> bool {
+        self.path == other.path
+    }
+}
+
+impl<'ast, T> Ord for Node<'ast, T> {
+    fn cmp(&self, other: &Node<'ast, T>) -> Ordering {
+        self.path.cmp(&other.path)
+    }
+}
+
+impl<'ast, T> PartialOrd for Node<'ast, T> {
+    fn partial_cmp(&self, other: &Node<'ast, T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'ast, T> Eq for Node<'ast, T> {}
+
+impl<'ast, T: Queryable> Node<'ast, T> {
+    pub fn kind(&self) -> NodeKind {
+        self.data.kind()
+    }
+    /// Computed from the node's tokens on every call.
+    pub fn location(&self) -> Option<Location> {
+        self.data.location()
+    }
+    pub fn to_source(&self) -> String {
+        self.render(Render::Tokens)
+    }
+    pub fn render(&self, render: Render) -> String {
+        render.node(self.data, self.location())
+    }
+    pub fn error(&self, message: &str) -> TokenStream {
+        error::compile_error(message, self.location())
+    }
+    pub fn into_owned(self) -> ::Node<T> {
+        ::Node {
+            data: self.data.to_owned(),
+            location: self.data.location(),
+            path: self.path,
+        }
+    }
+}
+
+fn path_of<T>(node: &Node<T>) -> Vec<i64> {
+    node.path.to_owned()
+}
+
+impl<'ast, T: Queryable> Collect<'ast> for Vec<Node<'ast, T>> {
+    fn collect<X: Queryable>(&mut self, node: &'ast X, base: &[i64], path: &[i64]) -> bool {
+        (node as &'ast dyn Any)
+            .downcast_ref::<T>()
+            .map(|data| {
+                self.push(Node {
+                    data,
+                    path: join_path(base, path),
+                })
+            })
+            .is_some()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryResult<'ast, T: 'ast, R: 'ast> {
+    pub(crate) nodes: Vec<Node<'ast, T>>,
+    pub(crate) root: &'ast R,
+    pub(crate) document: Rc<OnceCell<Document>>,
+}
+
+impl<'ast, T: Queryable, R: Queryable> OpsIndex<usize> for QueryResult<'ast, T, R> {
+    type Output = Node<'ast, T>;
+    fn index(&self, id: usize) -> &Node<'ast, T> {
+        &(self.nodes[id])
+    }
+}
+
+impl<'ast, T: Queryable, R: Queryable> QueryResult<'ast, T, R> {
+    pub fn query<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        let mut result = BTreeSet::new();
+        for i in self.iter() {
+            for j in i.data.visit_ref(i.path.to_owned(), None) {
+                result.insert(j);
+            }
+        }
+        self.with_nodes(result.into_iter().collect())
+    }
+    pub fn find<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        self.query()
+    }
+    pub fn children<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        let mut result = BTreeSet::new();
+        for i in self.iter() {
+            for j in i.data.visit_ref(i.path.to_owned(), Some(1)) {
+                result.insert(j);
+            }
+        }
+        self.with_nodes(result.into_iter().collect())
+    }
+    pub fn new(result: Vec<Node<'ast, T>>, root: &'ast R) -> QueryResult<'ast, T, R> {
+        QueryResult {
+            nodes: result,
+            root,
+            document: Rc::new(OnceCell::new()),
+        }
+    }
+    fn with_nodes<U>(&self, nodes: Vec<Node<'ast, U>>) -> QueryResult<'ast, U, R> {
+        QueryResult {
+            nodes,
+            root: self.root,
+            document: self.document.clone(),
+        }
+    }
+    pub fn root(&self) -> &'ast R {
+        self.root
+    }
+    /// Index of the root, built on first use and shared with every result
+    /// derived from this one.
+    pub fn document(&self) -> &Document {
+        self.document.get_or_init(|| Document::new(self.root))
+    }
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    pub fn locations(&self) -> Vec<Option<Location>> {
+        self.iter().map(|node| node.location()).collect()
+    }
+    pub fn error(&self, message: &str) -> TokenStream {
+        self.iter().map(|node| node.error(message)).collect()
+    }
+    pub fn iter(&self) -> ::std::slice::Iter<'_, Node<'ast, T>> {
+        self.nodes.iter()
+    }
+    pub fn filter<P>(&self, mut predicate: P) -> QueryResult<'ast, T, R>
+    where
+        for<'r> P: FnMut(&'r Node<'ast, T>) -> bool,
+    {
+        self.with_nodes(
+            self.iter()
+                .filter(|node| predicate(node))
+                .cloned()
+                .collect(),
+        )
+    }
+    fn entries<U: Queryable>(&self, ids: BTreeSet<usize>) -> QueryResult<'ast, U, R> {
+        let document = self.document();
+        let paths = ids.into_iter().map(|id| document.path(id)).collect();
+        self.with_nodes(fetch(self.root, paths))
+    }
+    fn paths(&self) -> impl Iterator<Item = &Vec<i64>> {
+        self.iter().map(|node| &node.path)
+    }
+    pub fn parents<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        self.entries(self.document().parents(self.paths()))
+    }
+    pub fn parent<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        self.entries(self.document().parent(self.paths()))
+    }
+    /// Nearest ancestor-or-self of type `U` of each node.
+    pub fn closest<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        self.entries(self.document().closest(self.paths(), true, is::<U>()))
+    }
+    pub fn prev<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        self.entries(self.document().prev(self.paths()))
+    }
+    pub fn prev_all<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        self.entries(self.document().prev_all(self.paths()))
+    }
+    pub fn prev_until<U: Queryable, P>(&self, predicate: P) -> QueryResult<'ast, U, R>
+    where
+        for<'r> P: FnMut(&'r Node<'ast, U>) -> bool,
+    {
+        let all: QueryResult<U, R> = self.prev_all();
+        let until = Axis::prev_until(all.filter(predicate).paths());
+        all.filter(|node| until.contains(&node.path))
+    }
+    pub fn next<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        self.entries(self.document().next(self.paths()))
+    }
+    pub fn next_all<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        self.entries(self.document().next_all(self.paths()))
+    }
+    pub fn next_until<U: Queryable, P>(&self, predicate: P) -> QueryResult<'ast, U, R>
+    where
+        for<'r> P: FnMut(&'r Node<'ast, U>) -> bool,
+    {
+        let all: QueryResult<U, R> = self.next_all();
+        let until = Axis::next_until(all.filter(predicate).paths());
+        all.filter(|node| until.contains(&node.path))
+    }
+    pub fn siblings<U: Queryable>(&self) -> QueryResult<'ast, U, R> {
+        self.entries(self.document().siblings(self.paths()))
+    }
+    pub fn eq(&self, index: isize) -> Option<Node<'ast, T>> {
+        let id = if index >= 0 {
+            index as usize
+        } else {
+            let offset = -index as usize;
+            if offset > self.len() {
+                return None;
+            }
+            self.len() - offset
+        };
+        self.nodes.get(id).cloned()
+    }
+    pub fn first(&self) -> Option<Node<'ast, T>> {
+        self.nodes.first().cloned()
+    }
+    pub fn last(&self) -> Option<Node<'ast, T>> {
+        self.nodes.last().cloned()
+    }
+    pub fn map<B, F>(&self, f: F) -> Vec<B>
+    where
+        F: FnMut(Node<'ast, T>) -> B,
+    {
+        self.nodes.iter().cloned().map(f).collect()
+    }
+    pub fn is<F>(&self, f: F) -> bool
+    where
+        for<'r> F: FnMut(&'r Node<'ast, T>) -> bool,
+    {
+        self.iter().any(f)
+    }
+    pub fn has(&self) -> bool {
+        !self.is_empty()
+    }
+    pub fn not<P>(&self, mut predicate: P) -> QueryResult<'ast, T, R>
+    where
+        for<'r> P: FnMut(&'r Node<'ast, T>) -> bool,
+    {
+        self.filter(|p: &Node<'ast, T>| !(predicate(p)))
+    }
+    /// Keeps the nodes with a descendant of type `U`.
+    pub fn has_descendant<U: Queryable>(&self) -> QueryResult<'ast, T, R> {
+        self.has_descendant_matching::<U, _>(|_| true)
+    }
+    pub fn has_descendant_matching<U: Queryable, P>(
+        &self,
+        mut predicate: P,
+    ) -> QueryResult<'ast, T, R>
+    where
+        for<'r> P: FnMut(&'r Node<'ast, U>) -> bool,
+    {
+        self.filter(|node| {
+            node.data
+                .visit_ref::<U>(node.path.to_owned(), None)
+                .iter()
+                .any(|inner| inner.path != node.path && predicate(inner))
+        })
+    }
+    /// Keeps the nodes that are or contain the identifier `name`.
+    pub fn contains_ident(&self, name: &str) -> QueryResult<'ast, T, R> {
+        self.filter(|node| {
+            node.data
+                .visit_ref::<Ident>(Vec::new(), None)
+                .iter()
+                .any(|ident| *ident.data == name)
+        })
+    }
+    /// Keeps the nodes whose code, as printed by `Node::to_source`,
+    /// contains `text`.
+    pub fn contains_text(&self, text: &str) -> QueryResult<'ast, T, R> {
+        self.filter(|node| node.to_source().contains(text))
+    }
+    /// Sorts the nodes in document order and drops duplicates.
+    pub fn unique(&self) -> QueryResult<'ast, T, R> {
+        self.with_nodes(set::combine(&self.nodes, &[], path_of, |_, _| true))
+    }
+    /// Borrowed results have the same root only when they borrow the same
+    /// one.
+    pub fn same_root(&self, other: &QueryResult<'ast, T, R>) -> Result<(), RootMismatch> {
+        if ptr::eq(self.root, other.root) {
+            Ok(())
+        } else {
+            Err(RootMismatch::Different)
+        }
+    }
+    pub fn union(
+        &self,
+        other: &QueryResult<'ast, T, R>,
+    ) -> Result<QueryResult<'ast, T, R>, RootMismatch> {
+        self.combine(other, |left, right| left || right)
+    }
+    pub fn intersection(
+        &self,
+        other: &QueryResult<'ast, T, R>,
+    ) -> Result<QueryResult<'ast, T, R>, RootMismatch> {
+        self.combine(other, |left, right| left && right)
+    }
+    pub fn difference(
+        &self,
+        other: &QueryResult<'ast, T, R>,
+    ) -> Result<QueryResult<'ast, T, R>, RootMismatch> {
+        self.combine(other, |left, right| left && !right)
+    }
+    pub fn symmetric_difference(
+        &self,
+        other: &QueryResult<'ast, T, R>,
+    ) -> Result<QueryResult<'ast, T, R>, RootMismatch> {
+        self.combine(other, |left, right| left != right)
+    }
+    fn combine<P>(
+        &self,
+        other: &QueryResult<'ast, T, R>,
+        keep: P,
+    ) -> Result<QueryResult<'ast, T, R>, RootMismatch>
+    where
+        P: Fn(bool, bool) -> bool,
+    {
+        self.same_root(other)?;
+        Ok(self.with_nodes(set::combine(&self.nodes, &other.nodes, path_of, keep)))
+    }
+    /// Clones the matches and the root into an owned `QueryResult`, which
+    /// keeps sharing the `Document`.
+    pub fn into_owned(self) -> ::QueryResult<T, R> {
+        let mut result = ::QueryResult::new(
+            self.nodes.into_iter().map(Node::into_owned).collect(),
+            self.root.to_owned(),
+        );
+        result.document = self.document;
+        result
+    }
+}
+
+impl<'ast, T: Queryable, R: Queryable> IntoIterator for QueryResult<'ast, T, R> {
+    type Item = Node<'ast, T>;
+    type IntoIter = ::std::vec::IntoIter<Node<'ast, T>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.nodes.into_iter()
+    }
+}
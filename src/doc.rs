@@ -0,0 +1,237 @@
+use attrs;
+use borrowed;
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+use sealed::Sealed;
+use std::any::Any;
+use syn::{AttrStyle, Attribute, Block, Lit, Meta, Visibility};
+use {join_path, Collect, DynNode, Location, Node, NodeKind, Position, Query, Queryable};
+
+/// Path segment between a node and its documentation: its `i`th
+/// `DocComment` is at the node's path followed by `DOC_COMMENT, i`.
+pub const DOC_COMMENT: i64 = -3;
+
+/// The documentation of a node, read from its `///`, `//!`, `/** */` and
+/// `/*! */` comments and `#[doc = "..."]` attributes, which syn gives as
+/// `doc` attributes.
+///
+/// It is not part of the syntax tree but queries for `DocComment` or its
+/// `NodeKind` find it as a child of the node it documents, see
+/// `DOC_COMMENT`. Queries of every kind, such as `query_dyn` or `*`, leave
+/// it out, and it is not part of the `Document`.
+#[derive(Debug, Clone)]
+pub struct DocComment {
+    /// The lines of the comments without their markers, joined by newlines.
+    pub text: String,
+    /// `Inner` for `//!` and `/*! */`.
+    pub style: AttrStyle,
+    /// Whether it is written with block comments only.
+    pub block: bool,
+    /// The node documented.
+    pub owner: Node<DynNode>,
+    pub attrs: Vec<Node<Attribute>>,
+    /// From the first comment to the last.
+    pub location: Option<Location>,
+}
+
+fn is_inner(style: &AttrStyle) -> bool {
+    match *style {
+        AttrStyle::Inner(_) => true,
+        AttrStyle::Outer => false,
+    }
+}
+
+/// The text of a `doc` attribute, as written for a block comment.
+pub(crate) fn value(attr: &Attribute) -> Option<String> {
+    if attrs::name(&attr.path) != "doc" {
+        return None;
+    }
+    match attr.interpret_meta() {
+        Some(Meta::NameValue(pair)) => match pair.lit {
+            Lit::Str(text) => Some(text.value()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `/** text */` without its markers and the ` * ` starting its lines.
+fn unwrap_block(comment: &str) -> String {
+    let body = comment
+        .trim_start_matches("/**")
+        .trim_start_matches("/*!")
+        .trim_end_matches("*/");
+    let lines: Vec<&str> = body
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            match trimmed.strip_prefix('*') {
+                Some(rest) => rest.strip_prefix(' ').unwrap_or(rest),
+                None => line.strip_prefix(' ').unwrap_or(line),
+            }
+        })
+        .collect();
+    lines.join("\n").trim().to_owned()
+}
+
+/// The outer and inner documentation of `owner`, when it has any.
+pub(crate) fn comments(owner: &Node<DynNode>) -> Vec<DocComment> {
+    group(owner, attrs::own(&owner.data, &owner.path))
+}
+
+/// The `doc` attributes among `attrs` of `owner`, joined by style.
+fn group(owner: &Node<DynNode>, attrs: Vec<Node<Attribute>>) -> Vec<DocComment> {
+    let mut comments: Vec<DocComment> = Vec::new();
+    for attr in attrs {
+        let text = match value(&attr.data) {
+            Some(text) => text,
+            None => continue,
+        };
+        let block = text.starts_with("/*");
+        let line = if block {
+            unwrap_block(&text)
+        } else {
+            text.strip_prefix(' ').unwrap_or(&text).to_owned()
+        };
+        let inner = is_inner(&attr.data.style);
+        match comments
+            .iter_mut()
+            .find(|comment| is_inner(&comment.style) == inner)
+        {
+            Some(comment) => {
+                comment.text.push('\n');
+                comment.text.push_str(&line);
+                comment.block = comment.block && block;
+                comment.location = match (comment.location, attr.location) {
+                    (Some(first), Some(last)) => {
+                        Some(Location::new(first.start_span, last.end_span))
+                    }
+                    (first, last) => first.or(last),
+                };
+                comment.attrs.push(attr);
+            }
+            None => comments.push(DocComment {
+                text: line,
+                style: attr.data.style.to_owned(),
+                block,
+                owner: owner.to_owned(),
+                location: attr.location,
+                attrs: vec![attr],
+            }),
+        }
+    }
+    comments
+}
+
+/// The paths of the nodes whose descendants are not public: the blocks,
+/// and the nodes with a visibility other than `pub`.
+pub(crate) fn hidden<R: Queryable>(root: &R) -> Vec<Vec<i64>> {
+    let mut hidden: Vec<Vec<i64>> = root
+        .visit_ref::<Visibility>(Vec::new(), None)
+        .into_iter()
+        .filter(|vis| !matches!(*vis.data, Visibility::Public(_)))
+        .filter_map(|vis| vis.path.split_last().map(|(_, owner)| owner.to_vec()))
+        .collect();
+    let blocks = root.visit_ref::<Block>(Vec::new(), None);
+    hidden.extend(blocks.into_iter().map(|block| block.path));
+    hidden
+}
+
+/// Collects the documentation of `owner`, the node `query` is at.
+pub(crate) fn visit<'ast, C: Collect<'ast>, X: Queryable>(query: &mut Query<C>, owner: &X) {
+    let wanted = match query.kinds {
+        Some(ref kinds) => kinds.contains(&NodeKind::DocComment),
+        None => query.results.wants(NodeKind::DocComment),
+    };
+    if query.targets.is_some() || !wanted {
+        return;
+    }
+    let path = join_path(&query.base, &query.path);
+    let mut attrs: Vec<Node<Attribute>> = owner.visit(path.to_owned(), Some(1));
+    attrs.retain(|attr| attr.path.len() > path.len());
+    if attrs.iter().all(|attr| value(&attr.data).is_none()) {
+        return;
+    }
+    let owner = Node {
+        data: owner.to_dyn(),
+        path,
+        location: owner.location(),
+    };
+    for (i, comment) in group(&owner, attrs).into_iter().enumerate() {
+        query.path.push(DOC_COMMENT);
+        query.path.push(i as i64);
+        if query.position() == Position::After
+            && query.accepts(NodeKind::DocComment)
+            && query
+                .results
+                .collect_owned(comment, &query.base, &query.path)
+        {
+            query.found += 1;
+        }
+        query.path.pop();
+        query.path.pop();
+    }
+}
+
+impl Sealed for DocComment {}
+
+impl Queryable for DocComment {
+    fn visit_kinds<U: Queryable>(
+        &self,
+        base: Vec<i64>,
+        _deep: Option<usize>,
+        kinds: Option<&[NodeKind]>,
+    ) -> Vec<Node<U>> {
+        if !kinds.is_none_or(|kinds| kinds.contains(&NodeKind::DocComment)) {
+            return Vec::new();
+        }
+        U::downcast_from(self)
+            .map(|data| Node {
+                data,
+                path: base,
+                location: self.location,
+            })
+            .into_iter()
+            .collect()
+    }
+    fn visit_after<U: Queryable>(
+        &self,
+        base: Vec<i64>,
+        deep: Option<usize>,
+        after: Option<&[i64]>,
+        limit: Option<usize>,
+    ) -> Vec<Node<U>> {
+        if after.is_some() || limit == Some(0) {
+            return Vec::new();
+        }
+        self.visit(base, deep)
+    }
+    fn visit_ref<'ast, U: Queryable>(
+        &'ast self,
+        base: Vec<i64>,
+        _deep: Option<usize>,
+    ) -> Vec<borrowed::Node<'ast, U>> {
+        (self as &dyn Any)
+            .downcast_ref::<U>()
+            .map(|data| borrowed::Node { data, path: base })
+            .into_iter()
+            .collect()
+    }
+    fn to_dyn(&self) -> DynNode {
+        DynNode::DocComment(Box::new(self.clone()))
+    }
+    fn kind(&self) -> NodeKind {
+        NodeKind::DocComment
+    }
+    fn location(&self) -> Option<Location> {
+        self.location
+    }
+    /// The `doc` attributes.
+    fn tokens(&self) -> Option<TokenStream> {
+        let mut tokens = TokenStream::new();
+        for attr in &self.attrs {
+            attr.data.to_tokens(&mut tokens);
+        }
+        Some(tokens)
+    }
+}
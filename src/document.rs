@@ -0,0 +1,329 @@
+use node_path::steps;
+use scope::Resolution;
+use std::any::Any;
+use std::cell::OnceCell;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Index as OpsIndex;
+use {
+    walk, Collect, Location, NodeKind, NodePath, Queryable, Step, ATTR_META, DOC_COMMENT,
+    MACRO_BODY,
+};
+
+/// One node of a `Document`, linked to its parent and children by id. The
+/// node itself stays in the root, it is fetched by path when needed.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub kind: NodeKind,
+    pub depth: usize,
+    pub step: Option<Step>,
+    pub location: Option<Location>,
+}
+
+/// Index of every node under a root, built once and shared by the
+/// `QueryResult`s derived from it.
+///
+/// Ids are assigned in document order, so sorting ids sorts by path; the
+/// root itself is id 0. The last index of a node's path is its position
+/// among the children of its parent, which is how paths and ids map to
+/// each other.
+#[derive(Debug, Clone)]
+pub struct Document {
+    entries: Vec<Entry>,
+    resolution: OnceCell<Resolution>,
+}
+
+/// Builds the entries in visiting order, keeping the ids of the ancestors
+/// of the current node.
+#[derive(Default)]
+struct Builder {
+    entries: Vec<Entry>,
+    ancestors: Vec<usize>,
+}
+
+impl<'ast> Collect<'ast> for Builder {
+    fn collect<X: Queryable>(&mut self, node: &'ast X, base: &[i64], path: &[i64]) -> bool {
+        let depth = base.len() + path.len();
+        let id = self.entries.len();
+        self.ancestors.truncate(depth);
+        let parent = self.ancestors.last().cloned();
+        if let Some(parent) = parent {
+            self.entries[parent].children.push(id);
+        }
+        self.entries.push(Entry {
+            parent,
+            children: Vec::new(),
+            kind: node.kind(),
+            depth,
+            step: None,
+            location: node.location(),
+        });
+        self.ancestors.push(id);
+        true
+    }
+}
+
+impl Document {
+    pub fn new<R: Queryable>(root: &R) -> Document {
+        let mut builder: Builder = walk(root, None);
+        let steps = steps(root);
+        debug_assert_eq!(builder.entries.len(), steps.len());
+        for (entry, step) in builder.entries.iter_mut().zip(steps) {
+            entry.step = step;
+        }
+        Document {
+            entries: builder.entries,
+            resolution: OnceCell::new(),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn id(&self, path: &[i64]) -> Option<usize> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let mut id = 0;
+        for &index in path {
+            if index < 0 {
+                return None;
+            }
+            id = *self.entries[id].children.get(index as usize)?;
+        }
+        Some(id)
+    }
+    pub fn path(&self, id: usize) -> Vec<i64> {
+        let mut path = Vec::with_capacity(self.entries[id].depth);
+        let mut current = id;
+        while let Some((parent, position)) = self.position(current) {
+            path.push(position as i64);
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+    pub fn node_path(&self, id: usize) -> NodePath {
+        let mut steps = Vec::new();
+        let mut current = Some(id);
+        while let Some(id) = current {
+            steps.extend(self.entries[id].step);
+            current = self.entries[id].parent;
+        }
+        steps.reverse();
+        NodePath {
+            root: self.entries[0].kind,
+            steps,
+        }
+    }
+    pub fn resolve(&self, path: &NodePath) -> Option<usize> {
+        if self.entries.first()?.kind != path.root {
+            return None;
+        }
+        let mut id = 0;
+        for step in &path.steps {
+            id = *self.entries[id]
+                .children
+                .iter()
+                .find(|&&child| self.entries[child].step.as_ref() == Some(step))?;
+        }
+        Some(id)
+    }
+    /// What the identifiers refer to, resolved on first use; `root` must
+    /// be the root the document was built from.
+    pub(crate) fn resolution(&self, root: &dyn Any) -> &Resolution {
+        self.resolution.get_or_init(|| Resolution::new(self, root))
+    }
+    pub(crate) fn ids<'a, I>(&self, paths: I) -> Vec<usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        paths.into_iter().filter_map(|path| self.id(path)).collect()
+    }
+    /// Position of `id` among its siblings and the id of its parent.
+    fn position(&self, id: usize) -> Option<(usize, usize)> {
+        let parent = self.entries[id].parent?;
+        let children = &self.entries[parent].children;
+        children
+            .binary_search(&id)
+            .ok()
+            .map(|position| (parent, position))
+    }
+    pub(crate) fn parents<'a, I>(&self, paths: I) -> BTreeSet<usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        let mut result = BTreeSet::new();
+        for path in paths {
+            let mut parent = self.parent_of(path);
+            while let Some(id) = parent {
+                if !result.insert(id) {
+                    break;
+                }
+                parent = self.entries[id].parent;
+            }
+        }
+        result
+    }
+    pub(crate) fn parent<'a, I>(&self, paths: I) -> BTreeSet<usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        paths
+            .into_iter()
+            .filter_map(|path| self.parent_of(path))
+            .collect()
+    }
+    /// The parent of the node at `path`. A node outside the document
+    /// directly below one, such as a `Meta` at `ATTR_META`, a `DocComment`
+    /// at `DOC_COMMENT` or a node parsed from a macro at `MACRO_BODY`, has
+    /// that node as its parent.
+    fn parent_of(&self, path: &[i64]) -> Option<usize> {
+        if let Some(id) = self.id(path) {
+            return self.entries[id].parent;
+        }
+        let split = path.iter().position(|&index| index < 0)?;
+        match path[split..] {
+            [ATTR_META] | [DOC_COMMENT, _] | [MACRO_BODY, _] => self.id(&path[..split]),
+            _ => None,
+        }
+    }
+    /// Nearest ancestor of each node accepted by `matches`, starting from
+    /// the node itself when `inclusive`.
+    pub(crate) fn closest<'a, I, F>(&self, paths: I, inclusive: bool, matches: F) -> BTreeSet<usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+        F: Fn(&Entry) -> bool,
+    {
+        self.ids(paths)
+            .into_iter()
+            .filter_map(|id| {
+                let mut current = if inclusive {
+                    Some(id)
+                } else {
+                    self.entries[id].parent
+                };
+                while let Some(id) = current {
+                    if matches(&self.entries[id]) {
+                        return Some(id);
+                    }
+                    current = self.entries[id].parent;
+                }
+                None
+            })
+            .collect()
+    }
+    pub(crate) fn prev<'a, I>(&self, paths: I) -> BTreeSet<usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        self.ids(paths)
+            .into_iter()
+            .filter_map(|id| self.position(id))
+            .filter(|&(_, position)| position > 0)
+            .map(|(parent, position)| self.entries[parent].children[position - 1])
+            .collect()
+    }
+    pub(crate) fn next<'a, I>(&self, paths: I) -> BTreeSet<usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        self.ids(paths)
+            .into_iter()
+            .filter_map(|id| self.position(id))
+            .filter_map(|(parent, position)| {
+                self.entries[parent].children.get(position + 1).cloned()
+            })
+            .collect()
+    }
+    /// The position among its siblings of the first or last of `paths`
+    /// under each parent, as chosen by `pick`.
+    fn bounds<'a, I, F>(&self, paths: I, pick: F) -> HashMap<usize, usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+        F: Fn(usize, usize) -> usize,
+    {
+        let mut bounds = HashMap::new();
+        for (parent, position) in self
+            .ids(paths)
+            .into_iter()
+            .filter_map(|id| self.position(id))
+        {
+            let bound = bounds.entry(parent).or_insert(position);
+            *bound = pick(*bound, position);
+        }
+        bounds
+    }
+    /// The siblings before the first of `paths` under each parent.
+    pub(crate) fn prev_all<'a, I>(&self, paths: I) -> BTreeSet<usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        let mut result = BTreeSet::new();
+        for (parent, bound) in self.bounds(paths, usize::min) {
+            result.extend(&self.entries[parent].children[..bound]);
+        }
+        result
+    }
+    /// The siblings after the last of `paths` under each parent.
+    pub(crate) fn next_all<'a, I>(&self, paths: I) -> BTreeSet<usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        let mut result = BTreeSet::new();
+        for (parent, bound) in self.bounds(paths, usize::max) {
+            result.extend(&self.entries[parent].children[bound + 1..]);
+        }
+        result
+    }
+    /// The siblings after any of `paths`, for the `~` selector combinator.
+    pub(crate) fn following<'a, I>(&self, paths: I) -> BTreeSet<usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        let mut result = BTreeSet::new();
+        for (parent, bound) in self.bounds(paths, usize::min) {
+            result.extend(&self.entries[parent].children[bound + 1..]);
+        }
+        result
+    }
+    /// Siblings of a node selected alone exclude it; when several children
+    /// of the same parent are selected, all of its children are returned.
+    pub(crate) fn siblings<'a, I>(&self, paths: I) -> BTreeSet<usize>
+    where
+        I: IntoIterator<Item = &'a Vec<i64>>,
+    {
+        let mut selected = HashMap::<usize, Option<usize>>::new();
+        for id in self.ids(paths) {
+            if let Some(parent) = self.entries[id].parent {
+                let only = selected.entry(parent).or_insert(Some(id));
+                if *only != Some(id) {
+                    *only = None;
+                }
+            }
+        }
+        let mut result = BTreeSet::new();
+        for (parent, only) in selected {
+            result.extend(
+                self.entries[parent]
+                    .children
+                    .iter()
+                    .filter(|&&child| Some(child) != only),
+            );
+        }
+        result
+    }
+}
+
+impl OpsIndex<usize> for Document {
+    type Output = Entry;
+    fn index(&self, id: usize) -> &Entry {
+        &self.entries[id]
+    }
+}
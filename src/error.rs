@@ -0,0 +1,83 @@
+use proc_macro2::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use std::iter::FromIterator;
+use {Location, Node, QueryResult, Queryable};
+
+/// `compile_error!("message")` spanning `location`, from its first token to
+/// its last, or at the call site when there is none.
+pub(crate) fn compile_error(message: &str, location: Option<Location>) -> TokenStream {
+    let (start, end) = match location {
+        Some(location) => (location.start_span, location.end_span),
+        None => (Span::call_site(), Span::call_site()),
+    };
+    let mut bang = Punct::new('!', Spacing::Alone);
+    bang.set_span(start);
+    let mut literal = Literal::string(message);
+    literal.set_span(end);
+    let mut group = Group::new(
+        Delimiter::Brace,
+        TokenStream::from_iter(vec![TokenTree::from(literal)]),
+    );
+    group.set_span(end);
+    TokenStream::from_iter(vec![
+        TokenTree::from(Ident::new("compile_error", start)),
+        TokenTree::from(bang),
+        TokenTree::from(group),
+    ])
+}
+
+/// Errors gathered while a procedural macro checks its input, to report
+/// them all at once.
+#[derive(Debug, Clone, Default)]
+pub struct Errors {
+    errors: Vec<TokenStream>,
+}
+
+impl Errors {
+    pub fn new() -> Errors {
+        Errors::default()
+    }
+    pub fn push<T: Queryable>(&mut self, node: &Node<T>, message: &str) -> &mut Errors {
+        self.errors.push(node.error(message));
+        self
+    }
+    /// Adds an error at each node of `result`.
+    pub fn extend<T: Queryable, R: Queryable>(
+        &mut self,
+        result: &QueryResult<T, R>,
+        message: &str,
+    ) -> &mut Errors {
+        for node in result.iter() {
+            self.push(node, message);
+        }
+        self
+    }
+    /// Adds the error of `check`, when there is one.
+    pub fn check<E: ToTokens>(&mut self, check: Result<(), E>) -> &mut Errors {
+        if let Err(error) = check {
+            self.errors.push(error.into_token_stream());
+        }
+        self
+    }
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+    /// `Err` with every error when there is any, for the macro to return
+    /// in place of its output.
+    pub fn finish(self) -> Result<(), TokenStream> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self.into_token_stream())
+        }
+    }
+}
+
+impl ToTokens for Errors {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(self.errors.iter().cloned());
+    }
+}
@@ -0,0 +1,55 @@
+use selection::Selection;
+use std::any::Any;
+use std::fmt;
+use std::rc::Rc;
+use {DynNode, Node, Queryable};
+
+/// Selections a `QueryResult` was derived from, the latest first.
+pub(crate) type History = Option<Rc<Frame>>;
+
+/// A previous selection, kept with its own node type so that `end` gives
+/// it back without converting it. The nodes are shared with the result
+/// that held them, not copied.
+pub(crate) struct Frame {
+    nodes: Box<dyn Any>,
+    to_dyn: fn(&dyn Any) -> Vec<Node<DynNode>>,
+    pub(crate) previous: History,
+}
+
+impl Frame {
+    pub(crate) fn push<T: Queryable>(nodes: &Rc<Selection<T>>, previous: &History) -> History {
+        Some(Rc::new(Frame {
+            nodes: Box::new(nodes.clone()),
+            to_dyn: to_dyn::<T>,
+            previous: previous.clone(),
+        }))
+    }
+    /// The selection as `U`s: all of it when it held `U`s, otherwise the
+    /// nodes of kind `U`.
+    pub(crate) fn nodes<U: Queryable>(&self) -> Rc<Selection<U>> {
+        match self.nodes.downcast_ref::<Rc<Selection<U>>>() {
+            Some(nodes) => nodes.clone(),
+            None => Rc::new(Selection::new(
+                (self.to_dyn)(&*self.nodes)
+                    .iter()
+                    .filter_map(|node| node.downcast())
+                    .collect(),
+            )),
+        }
+    }
+}
+
+fn to_dyn<T: Queryable>(nodes: &dyn Any) -> Vec<Node<DynNode>> {
+    nodes
+        .downcast_ref::<Rc<Selection<T>>>()
+        .map_or_else(Vec::new, |nodes| nodes.iter().map(Node::to_dyn).collect())
+}
+
+impl fmt::Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Frame")
+            .field("nodes", &(self.to_dyn)(&*self.nodes))
+            .field("previous", &self.previous)
+            .finish()
+    }
+}
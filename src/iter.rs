@@ -0,0 +1,131 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::mem;
+use {
+    accept_kind, accept_root, join_path, kind_of_type, Collect, Node, NodeKind, Query, Queryable,
+};
+
+/// Nodes of type `U` under a root in document order, found one at a time.
+///
+/// The nodes still to visit are kept on a stack: every call to `next` takes
+/// nodes off it, pushing their children, until one is a `U`, so each node
+/// is visited once and nothing after the last node taken is visited.
+#[derive(Debug, Clone)]
+pub struct QueryIter<'a, U, R: 'a> {
+    /// Nodes still to visit, the next one on top.
+    stack: Vec<Pending<'a>>,
+    /// The path of the node visited last.
+    path: Vec<i64>,
+    /// The children of the node visited last, kept for their allocation.
+    children: Vec<Pending<'a>>,
+    /// Nodes found but not returned yet, such as the `DocComment`s of the
+    /// node visited last.
+    found: VecDeque<Node<U>>,
+    /// The kind of `U`, `None` for `DynNode`.
+    kind: Option<NodeKind>,
+    marker: PhantomData<&'a R>,
+}
+
+/// A node on the stack of a `QueryIter`, the root has no kind or index.
+#[derive(Debug, Clone)]
+struct Pending<'a> {
+    node: &'a dyn Any,
+    kind: Option<NodeKind>,
+    /// The length of the path of its parent, and its index there.
+    depth: usize,
+    index: Option<i64>,
+}
+
+impl<'a, U: Queryable, R: Queryable> QueryIter<'a, U, R> {
+    pub fn new(root: &'a R, base: Vec<i64>) -> QueryIter<'a, U, R> {
+        QueryIter {
+            stack: vec![Pending {
+                node: root,
+                kind: None,
+                depth: base.len(),
+                index: None,
+            }],
+            path: base,
+            children: Vec::new(),
+            found: VecDeque::new(),
+            kind: kind_of_type::<U>(),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// A node and its children, reached by a `Query` one level deep.
+struct Step<'a, U> {
+    children: Vec<Pending<'a>>,
+    found: VecDeque<Node<U>>,
+    kind: Option<NodeKind>,
+}
+
+impl<'a, U: Queryable> Collect<'a> for Step<'a, U> {
+    fn collect<X: Queryable>(&mut self, node: &'a X, base: &[i64], path: &[i64]) -> bool {
+        if let Some(&index) = path.first() {
+            self.children.push(Pending {
+                node,
+                kind: Some(node.kind()),
+                depth: base.len(),
+                index: Some(index),
+            });
+        } else if let Some(data) = U::downcast_from(node) {
+            self.found.push_back(Node {
+                data,
+                path: base.to_vec(),
+                location: node.location(),
+            });
+        }
+        false
+    }
+    fn wants(&self, kind: NodeKind) -> bool {
+        self.kind == Some(kind)
+    }
+    fn collect_owned<X: Queryable>(&mut self, node: X, base: &[i64], path: &[i64]) -> bool {
+        if let Some(data) = U::downcast_from(&node) {
+            self.found.push_back(Node {
+                data,
+                path: join_path(base, path),
+                location: node.location(),
+            });
+        }
+        false
+    }
+}
+
+impl<'a, U: Queryable, R: Queryable> Iterator for QueryIter<'a, U, R> {
+    type Item = Node<U>;
+    fn next(&mut self) -> Option<Node<U>> {
+        loop {
+            if let Some(node) = self.found.pop_front() {
+                return Some(node);
+            }
+            let pending = self.stack.pop()?;
+            if let Some(index) = pending.index {
+                self.path.truncate(pending.depth);
+                self.path.push(index);
+            }
+            let step = Step {
+                children: mem::take(&mut self.children),
+                found: mem::take(&mut self.found),
+                kind: self.kind,
+            };
+            let mut query = Query::with_results(mem::take(&mut self.path), Some(1), None, step);
+            match pending.kind {
+                Some(kind) => accept_kind(&mut query, pending.node, kind),
+                None => accept_root(&mut query, pending.node),
+            }
+            self.path = query.base;
+            let Step {
+                mut children,
+                found,
+                ..
+            } = query.results;
+            self.stack.extend(children.drain(..).rev());
+            self.children = children;
+            self.found = found;
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use proc_macro2::Span;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use syn::token;
+use syn::{AttrStyle, Attribute, File, Item, ItemMod, Lit, Meta};
+use {doc, Location, Node, NodeKind, Queryable, DOC_COMMENT};
+
+/// A crate read from its root file and the files of its `mod foo;`
+/// declarations, which are spliced into the `ItemMod`s as if they were
+/// written inline. It derefs to that `File`, so the whole crate is queried
+/// as one tree; `file_of` and `locate` tell which file a node comes from.
+///
+/// `Node::location` holds the line and column in the node's own file but
+/// not the file itself, so locations of nodes from different files cannot
+/// be told apart without `locate`.
+#[derive(Debug, Clone)]
+pub struct Crate {
+    file: File,
+    /// The file of the root and of each item, inner attribute and `//!`
+    /// `DocComment` read from a module's file, by path in the tree.
+    files: Vec<(Vec<i64>, PathBuf)>,
+    /// The files read, in order, with their text.
+    sources: Vec<(PathBuf, String)>,
+    errors: Vec<CrateError>,
+}
+
+/// A file of a crate that could not be read or parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateError {
+    pub file: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for CrateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.file.display(), self.message)
+    }
+}
+
+impl Error for CrateError {}
+
+/// Where a node of a `Crate` is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLocation {
+    pub file: PathBuf,
+    pub location: Location,
+}
+
+impl fmt::Display for FileLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.location)
+    }
+}
+
+struct Loader {
+    sources: Vec<(PathBuf, String)>,
+    errors: Vec<CrateError>,
+    /// The file each module was read from, in the order of the `ItemMod`s
+    /// not nested in a function; `None` for inline modules.
+    modules: Vec<Option<Source>>,
+    /// The files being read, from the root to the current module, so that
+    /// a module including one of them again is reported instead of read.
+    loading: HashSet<PathBuf>,
+}
+
+/// A module read from its own file.
+struct Source {
+    file: PathBuf,
+    /// How many inner attributes of the file follow those of the `mod`
+    /// declaration in the module's `attrs`.
+    attrs: usize,
+    /// The index among the module's `DocComment`s of the file's `//!`
+    /// comments, when it has any.
+    doc: Option<i64>,
+}
+
+impl Loader {
+    fn parse(&mut self, file: &Path) -> Result<File, CrateError> {
+        let error = |message: String| CrateError {
+            file: file.to_owned(),
+            message,
+        };
+        let source = fs::read_to_string(file).map_err(|e| error(e.to_string()))?;
+        let parsed = syn::parse_file(&source).map_err(|e| error(e.to_string()))?;
+        self.sources.push((file.to_owned(), source));
+        Ok(parsed)
+    }
+    /// Reads the modules declared in `items`, whose own modules are looked
+    /// for in `dir`; `inline` when `items` are those of an inline module.
+    fn splice(&mut self, items: &mut [Item], dir: &Path, file: &Path, inline: bool) {
+        for item in items {
+            if let Item::Mod(ref mut module) = *item {
+                self.module(module, dir, file, inline);
+            }
+        }
+    }
+    fn module(&mut self, module: &mut ItemMod, dir: &Path, file: &Path, inline: bool) {
+        let name = module.ident.to_string();
+        if let Some((_, ref mut items)) = module.content {
+            self.modules.push(None);
+            return self.splice(items, &dir.join(&name), file, true);
+        }
+        // Within an inline module a `#[path]` is relative to the module's
+        // directory rather than to the file.
+        let candidates = match path_attribute(module) {
+            Some(ref path) if inline => vec![dir.join(path)],
+            Some(path) => vec![file.parent().unwrap_or(dir).join(path)],
+            None => vec![
+                dir.join(format!("{}.rs", name)),
+                dir.join(&name).join("mod.rs"),
+            ],
+        };
+        let found = match candidates.iter().find(|candidate| candidate.is_file()) {
+            Some(found) => found.to_owned(),
+            None => {
+                self.modules.push(None);
+                return self.errors.push(CrateError {
+                    file: file.to_owned(),
+                    message: format!("file not found for module `{}`", name),
+                });
+            }
+        };
+        let key = fs::canonicalize(&found).unwrap_or_else(|_| found.to_owned());
+        if self.loading.contains(&key) {
+            self.modules.push(None);
+            return self.errors.push(CrateError {
+                file: file.to_owned(),
+                message: format!(
+                    "module `{}` includes `{}`, which is already being read",
+                    name,
+                    found.display()
+                ),
+            });
+        }
+        let index = self.modules.len();
+        self.modules.push(Some(Source {
+            file: found.to_owned(),
+            attrs: 0,
+            doc: None,
+        }));
+        let parsed = match self.parse(&found) {
+            Ok(parsed) => parsed,
+            Err(error) => return self.errors.push(error),
+        };
+        self.loading.insert(key.to_owned());
+        let mut items = parsed.items;
+        let own_dir = match found.file_name() {
+            Some(file_name) if file_name != "mod.rs" && path_attribute(module).is_none() => {
+                dir.join(&name)
+            }
+            _ => found.parent().unwrap_or(dir).to_owned(),
+        };
+        self.splice(&mut items, &own_dir, &found, false);
+        self.loading.remove(&key);
+        // The inner attributes of the file are those of the module, and its
+        // braces stand where the `;` of the declaration was.
+        let is_doc = |attr: &Attribute| doc::value(attr).is_some();
+        if let Some(Some(ref mut source)) = self.modules.get_mut(index) {
+            source.attrs = parsed.attrs.len();
+            if parsed.attrs.iter().any(is_doc) {
+                let outer = module
+                    .attrs
+                    .iter()
+                    .any(|attr| is_doc(attr) && attr.style == AttrStyle::Outer);
+                source.doc = Some(outer as i64);
+            }
+        }
+        module.attrs.extend(parsed.attrs);
+        let brace = token::Brace(
+            module
+                .semi
+                .take()
+                .map_or_else(Span::call_site, |semi| semi.0[0]),
+        );
+        module.content = Some((brace, items));
+    }
+}
+
+fn path_attribute(module: &ItemMod) -> Option<String> {
+    module
+        .attrs
+        .iter()
+        .filter_map(|attr| attr.interpret_meta())
+        .find_map(|meta| match meta {
+            Meta::NameValue(ref pair) if pair.ident == "path" => match pair.lit {
+                Lit::Str(ref path) => Some(path.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+}
+
+impl Crate {
+    /// Loads the crate rooted at `path`: a `.rs` file, or a crate directory
+    /// holding `src/lib.rs` or `src/main.rs`. Fails only when the root
+    /// cannot be read or parsed; the errors of other files are kept in
+    /// `errors` and their modules left empty.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Crate, CrateError> {
+        let path = path.as_ref();
+        let root = if path.is_dir() {
+            ["src/lib.rs", "src/main.rs", "lib.rs", "main.rs"]
+                .iter()
+                .map(|file| path.join(file))
+                .find(|file| file.is_file())
+                .ok_or_else(|| CrateError {
+                    file: path.to_owned(),
+                    message: "no src/lib.rs or src/main.rs".to_owned(),
+                })?
+        } else {
+            path.to_owned()
+        };
+        let mut loader = Loader {
+            sources: Vec::new(),
+            errors: Vec::new(),
+            modules: Vec::new(),
+            loading: HashSet::new(),
+        };
+        let mut file = loader.parse(&root)?;
+        loader
+            .loading
+            .insert(fs::canonicalize(&root).unwrap_or_else(|_| root.to_owned()));
+        let dir = root.parent().unwrap_or_else(|| Path::new("")).to_owned();
+        loader.splice(&mut file.items, &dir, &root, false);
+
+        let mut files = vec![(Vec::new(), root)];
+        let document = file.query::<File>();
+        let document = document.document();
+        let modules = document.entries().iter().enumerate().filter(|&(_, entry)| {
+            entry.kind == NodeKind::ItemMod && {
+                let mut parent = entry.parent;
+                let mut top = true;
+                while let Some(id) = parent {
+                    top &= matches!(
+                        document[id].kind,
+                        NodeKind::File | NodeKind::Item | NodeKind::ItemMod
+                    );
+                    parent = document[id].parent;
+                }
+                top
+            }
+        });
+        for ((id, entry), module) in modules.zip(loader.modules) {
+            let module = match module {
+                Some(module) => module,
+                None => continue,
+            };
+            let attrs: Vec<usize> = entry
+                .children
+                .iter()
+                .cloned()
+                .filter(|&child| document[child].kind == NodeKind::Attribute)
+                .collect();
+            let inner = &attrs[attrs.len().saturating_sub(module.attrs)..];
+            let items = entry
+                .children
+                .iter()
+                .cloned()
+                .filter(|&child| document[child].kind == NodeKind::Item);
+            for child in inner.iter().cloned().chain(items) {
+                files.push((document.path(child), module.file.to_owned()));
+            }
+            if let Some(doc) = module.doc {
+                let mut path = document.path(id);
+                path.extend(&[DOC_COMMENT, doc]);
+                files.push((path, module.file));
+            }
+        }
+        Ok(Crate {
+            file,
+            files,
+            sources: loader.sources,
+            errors: loader.errors,
+        })
+    }
+    /// The files read, the root first.
+    pub fn files(&self) -> Vec<&Path> {
+        self.sources
+            .iter()
+            .map(|(file, _)| file.as_path())
+            .collect()
+    }
+    pub fn errors(&self) -> &[CrateError] {
+        &self.errors
+    }
+    /// The text of `file`, one of `files`.
+    pub fn source(&self, file: &Path) -> Option<&str> {
+        self.sources
+            .iter()
+            .find(|(read, _)| read == file)
+            .map(|(_, source)| source.as_str())
+    }
+    /// The file `node`, a node of this crate's tree, is written in. Its
+    /// `location` is within that file.
+    pub fn file_of<T>(&self, node: &Node<T>) -> Option<&Path> {
+        self.files
+            .iter()
+            .filter(|(path, _)| node.path.starts_with(path))
+            .max_by_key(|(path, _)| path.len())
+            .map(|(_, file)| file.as_path())
+    }
+    pub fn locate<T>(&self, node: &Node<T>) -> Option<FileLocation> {
+        Some(FileLocation {
+            file: self.file_of(node)?.to_owned(),
+            location: node.location?,
+        })
+    }
+    /// The text of `node` in its file.
+    pub fn text_of<T>(&self, node: &Node<T>) -> Option<&str> {
+        let source = self.source(self.file_of(node)?)?;
+        node.location?
+            .byte_range(source)
+            .map(|range| &source[range])
+    }
+}
+
+impl Deref for Crate {
+    type Target = File;
+    fn deref(&self) -> &File {
+        &self.file
+    }
+}
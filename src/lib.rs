@@ -29,11 +29,73 @@
 //! ```
 
 extern crate proc_macro2;
+extern crate quote;
 extern crate syn;
-use proc_macro2::Span;
-use std::any::Any;
+
+pub mod borrowed;
+
+mod attrs;
+mod axis;
+mod doc;
+mod document;
+mod error;
+mod history;
+mod iter;
+mod krate;
+mod lint;
+mod location;
+mod macros;
+mod mutate;
+mod node_path;
+mod pattern;
+mod render;
+mod rewrite;
+mod schema;
+mod scope;
+mod selection;
+mod selector;
+mod set;
+mod tokens;
+
+pub use attrs::ATTR_META;
+pub use doc::{DocComment, DOC_COMMENT};
+pub use document::{Document, Entry};
+pub use error::Errors;
+pub use iter::QueryIter;
+pub use krate::{Crate, CrateError, FileLocation};
+#[doc(hidden)]
+pub use lint::json_string;
+pub use lint::{Diagnostic, Lint, LintRunner, Severity};
+pub use location::Location;
+pub use macros::MACRO_BODY;
+pub use mutate::MutateError;
+pub use node_path::{NodePath, NodePathError, Step};
+pub use pattern::{Bindings, Match, Pattern, PatternError};
+pub use render::Render;
+pub use rewrite::{RewriteError, Rewriter};
+pub use selector::{Selector, SelectorError};
+pub use set::RootMismatch;
+pub use tokens::{FromTokenTree, TokenQueryable, TokenResult};
+
+use axis::Axis;
+use history::{Frame, History};
+use location::{tokens_range, TokenRange};
+use mutate::Mutate;
+use node_path::Edges;
+use schema::{Accept, Schema};
+use selection::Selection;
+
+use proc_macro2::{Span, TokenStream};
+use quote::ToTokens;
+use std::any::{Any, TypeId};
+use std::cell::OnceCell;
 use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Bound;
 use std::ops::Index as OpsIndex;
+use std::rc::Rc;
+use syn::synom::Synom;
 use syn::visit::*;
 use syn::*;
 
@@ -41,6 +103,7 @@ use syn::*;
 pub struct Node<T> {
     pub data: T,
     pub path: Vec<i64>,
+    pub location: Option<Location>,
 }
 
 impl<T> PartialEq for Node<T> {
@@ -63,38 +126,219 @@ impl<T> PartialOrd for Node<T> {
 
 impl<T> Eq for Node<T> {}
 
+impl<T: Queryable> Node<T> {
+    pub fn kind(&self) -> NodeKind {
+        self.data.kind()
+    }
+    pub fn to_dyn(&self) -> Node<DynNode> {
+        Node {
+            data: self.data.to_dyn(),
+            path: self.path.to_owned(),
+            location: self.location,
+        }
+    }
+    /// The node printed as Rust code on one line.
+    pub fn to_source(&self) -> String {
+        self.render(Render::Tokens)
+    }
+    pub fn render(&self, render: Render) -> String {
+        render.node(&self.data, self.location)
+    }
+    /// `compile_error!(message)` pointing at the node, to return from a
+    /// procedural macro.
+    pub fn error(&self, message: &str) -> TokenStream {
+        error::compile_error(message, self.location)
+    }
+}
+
+impl Node<DynNode> {
+    pub fn downcast<T: Queryable>(&self) -> Option<Node<T>> {
+        self.data.downcast().map(|data| Node {
+            data,
+            path: self.path.to_owned(),
+            location: self.location,
+        })
+    }
+}
+
+/// Receives every node reached by a `Query`, `base` and `path` together
+/// form the node path. Returns whether the node was kept.
+trait Collect<'ast> {
+    fn collect<X: Queryable>(&mut self, node: &'ast X, base: &[i64], path: &[i64]) -> bool;
+    /// Whether nodes of `kind` are kept by type. The nodes built during the
+    /// walk, such as `DocComment`s, are only built when wanted or when their
+    /// kind is among `Query::kinds`, so that walks over every kind, such as
+    /// those for `DynNode`, leave them out.
+    fn wants(&self, _kind: NodeKind) -> bool {
+        false
+    }
+    /// Like `collect`, for a node built during the walk.
+    fn collect_owned<X: Queryable>(&mut self, _node: X, _base: &[i64], _path: &[i64]) -> bool {
+        false
+    }
+}
+
+/// Kinds `offset_parent` stops at.
+const SCOPES: &[NodeKind] = &[
+    NodeKind::ExprClosure,
+    NodeKind::ImplItemMethod,
+    NodeKind::ItemFn,
+    NodeKind::ItemImpl,
+    NodeKind::ItemMod,
+    NodeKind::ItemTrait,
+    NodeKind::TraitItemMethod,
+];
+
+/// The nodes under `root` of one of `kinds`, or of every kind.
+fn walk<'ast, C: Collect<'ast> + Default>(root: &'ast dyn Any, kinds: Option<&[NodeKind]>) -> C {
+    let mut query = Query::<C>::new(Vec::new(), None, kinds);
+    accept_root(&mut query, root);
+    query.results
+}
+
+/// The nodes at `paths` under `root`, found in a single walk that only
+/// enters the subtrees holding them.
+fn fetch<'ast, C: Collect<'ast> + Default>(root: &'ast dyn Any, paths: BTreeSet<Vec<i64>>) -> C {
+    if paths.is_empty() {
+        return C::default();
+    }
+    let mut query = Query::<C>::new(Vec::new(), None, None);
+    query.targets = Some(paths);
+    accept_root(&mut query, root);
+    query.results
+}
+
+/// Whether entries hold a `U`, which every entry does for `DynNode`.
+fn is<U: Queryable>() -> impl Fn(&Entry) -> bool {
+    |entry| holds::<U>(entry.kind)
+}
+
+/// Whether a node of `kind` is a `U`.
+fn holds<U: Queryable>(kind: NodeKind) -> bool {
+    TypeId::of::<U>() == TypeId::of::<DynNode>() || kind_of_type::<U>() == Some(kind)
+}
+
+fn path_of<T>(node: &Node<T>) -> Vec<i64> {
+    node.path.to_owned()
+}
+
+fn join_path(base: &[i64], path: &[i64]) -> Vec<i64> {
+    base.iter().chain(path).cloned().collect()
+}
+
+impl<'ast, T: Queryable> Collect<'ast> for Vec<Node<T>> {
+    fn collect<X: Queryable>(&mut self, node: &'ast X, base: &[i64], path: &[i64]) -> bool {
+        T::downcast_from(node)
+            .map(|data| {
+                self.push(Node {
+                    data,
+                    path: join_path(base, path),
+                    location: node.location(),
+                })
+            })
+            .is_some()
+    }
+    fn wants(&self, kind: NodeKind) -> bool {
+        kind_of_type::<T>() == Some(kind)
+    }
+    fn collect_owned<X: Queryable>(&mut self, node: X, base: &[i64], path: &[i64]) -> bool {
+        Collect::collect(self, &node, base, path)
+    }
+}
+
 #[derive(Debug)]
-struct Query<T> {
+struct Query<C> {
     base: Vec<i64>,
     path: Vec<i64>,
-    results: Vec<Node<T>>,
+    results: C,
     deep: Option<usize>,
+    kinds: Option<Vec<NodeKind>>,
+    after: Option<Vec<i64>>,
+    limit: Option<usize>,
+    found: usize,
+    /// When set, only the nodes at these paths are collected and only the
+    /// subtrees holding them are entered.
+    targets: Option<BTreeSet<Vec<i64>>>,
+}
+
+/// Where the node being visited lies relative to `Query::after`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Position {
+    /// Before it and not an ancestor, the whole subtree is skipped.
+    Before,
+    /// It or one of its ancestors, only visited for the descendants.
+    Ancestor,
+    After,
+    /// `limit` nodes have been found.
+    Done,
+}
+
+impl<C: Default> Query<C> {
+    fn new(base: Vec<i64>, deep: Option<usize>, kinds: Option<&[NodeKind]>) -> Query<C> {
+        Query::with_results(base, deep, kinds, C::default())
+    }
 }
-impl<T: Queryable> Query<T> {
-    fn new(base: Vec<i64>, deep: Option<usize>) -> Query<T> {
+impl<C> Query<C> {
+    fn with_results(
+        base: Vec<i64>,
+        deep: Option<usize>,
+        kinds: Option<&[NodeKind]>,
+        results: C,
+    ) -> Query<C> {
         Query {
-            base: base,
+            base,
             path: Vec::new(),
-            results: Vec::new(),
-            deep: deep,
+            results,
+            deep,
+            kinds: kinds.map(|kinds| kinds.to_vec()),
+            after: None,
+            limit: None,
+            found: 0,
+            targets: None,
         }
     }
-    fn mk_result(&mut self, i: T) {
-        self.results.push(Node {
-            path: self.base
-                .clone()
-                .into_iter()
-                .chain(self.path.clone().into_iter())
-                .collect(),
-            data: i.clone(),
-        });
+    fn accepts(&self, kind: NodeKind) -> bool {
+        if let Some(ref targets) = self.targets {
+            if !targets.contains(&self.path) {
+                return false;
+            }
+        }
+        self.kinds.as_ref().is_none_or(|kinds| kinds.contains(&kind))
+    }
+    fn position(&self) -> Position {
+        if self.limit.is_some_and(|limit| self.found >= limit) {
+            return Position::Done;
+        }
+        if let Some(ref targets) = self.targets {
+            let path: &[i64] = &self.path;
+            let next = targets
+                .range::<[i64], _>((Bound::Included(path), Bound::Unbounded))
+                .next();
+            return if next.is_some_and(|target| target.starts_with(path)) {
+                Position::After
+            } else {
+                Position::Before
+            };
+        }
+        match self.after {
+            Some(ref after) if self.path <= *after => {
+                if after.starts_with(&self.path) {
+                    Position::Ancestor
+                } else {
+                    Position::Before
+                }
+            }
+            _ => Position::After,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct QueryResult<T, R> {
-    nodes: Vec<Node<T>>,
-    root: R,
+    nodes: Rc<Selection<T>>,
+    root: Rc<R>,
+    document: Rc<OnceCell<Document>>,
+    history: History,
 }
 
 impl<T: Queryable, R: Queryable> OpsIndex<usize> for QueryResult<T, R> {
@@ -105,247 +349,266 @@ impl<T: Queryable, R: Queryable> OpsIndex<usize> for QueryResult<T, R> {
 }
 
 impl<T: Queryable, R: Queryable> QueryResult<T, R> {
+    /// The nodes are searched for when first needed; `first`, `is`, `has`
+    /// and `eq` with a non-negative index stop at the node they look for.
     pub fn query<U: Queryable>(&self) -> QueryResult<U, R> {
-        use std::collections::BTreeSet;
-        let mut result = BTreeSet::new();
-        for i in self.iter() {
-            for j in i.data.visit(i.path.to_owned(), None) {
-                result.insert(j);
-            }
-        }
-        QueryResult::new(result.into_iter().collect(), self.root.to_owned())
+        self.with_selection(Selection::query(&self.nodes))
     }
     pub fn find<U: Queryable>(&self) -> QueryResult<U, R> {
         self.query()
     }
+    /// Like `query`, but also finds the nodes in the bodies of macro
+    /// invocations, parsed when they read as Rust code; see `MACRO_BODY`.
+    /// Those nodes are not part of the document.
+    pub fn query_macros<U: Queryable>(&self) -> QueryResult<U, R> {
+        let mut result = BTreeSet::new();
+        for node in self.iter() {
+            result.extend(macros::visit(&node.data, node.path.to_owned()));
+        }
+        self.with_nodes(result.into_iter().collect())
+    }
+    /// Lazy `query`, descendants of nested nodes are yielded once.
+    pub fn query_iter<U: Queryable>(&self) -> impl Iterator<Item = Node<U>> + '_ {
+        let mut covered: Option<&[i64]> = None;
+        self.iter()
+            .filter(move |node| {
+                if covered.is_some_and(|path| node.path.starts_with(path)) {
+                    return false;
+                }
+                covered = Some(&node.path);
+                true
+            })
+            .flat_map(|node| QueryIter::new(&node.data, node.path.to_owned()))
+    }
     pub fn children<U: Queryable>(&self) -> QueryResult<U, R> {
-        use std::collections::BTreeSet;
         let mut result = BTreeSet::new();
         for i in self.iter() {
             for j in i.data.visit(i.path.to_owned(), Some(1)) {
                 result.insert(j);
             }
         }
-        QueryResult::new(result.into_iter().collect(), self.root.to_owned())
+        self.with_nodes(result.into_iter().collect())
     }
     pub fn new(result: Vec<Node<T>>, root: R) -> QueryResult<T, R> {
         QueryResult {
-            nodes: result,
-            root: root,
+            nodes: Rc::new(Selection::new(result)),
+            root: Rc::new(root),
+            document: Rc::new(OnceCell::new()),
+            history: None,
+        }
+    }
+    /// A result derived from this one, which `end` returns to.
+    fn with_nodes<U: Queryable>(&self, nodes: Vec<Node<U>>) -> QueryResult<U, R> {
+        self.with_selection(Selection::new(nodes))
+    }
+    fn with_selection<U>(&self, nodes: Selection<U>) -> QueryResult<U, R> {
+        QueryResult {
+            nodes: Rc::new(nodes),
+            root: self.root.clone(),
+            document: self.document.clone(),
+            history: Frame::push(&self.nodes, &self.history),
+        }
+    }
+    /// The selection this one was derived from, as `U`s; only its nodes of
+    /// kind `U` are kept when it held another type. Empty at the start of a
+    /// chain.
+    pub fn end<U: Queryable>(&self) -> QueryResult<U, R> {
+        let (nodes, history) = match self.history {
+            Some(ref frame) => (frame.nodes(), frame.previous.clone()),
+            None => (Rc::new(Selection::new(Vec::new())), None),
+        };
+        QueryResult {
+            nodes,
+            root: self.root.clone(),
+            document: self.document.clone(),
+            history,
         }
     }
+    /// The current selection together with the one it was derived from, in
+    /// document order.
+    pub fn add_back(&self) -> QueryResult<DynNode, R> {
+        let nodes: Vec<Node<DynNode>> = self.iter().map(Node::to_dyn).collect();
+        let previous = self.end::<DynNode>();
+        self.with_nodes(set::combine(&nodes, &previous.nodes, path_of, |_, _| true))
+    }
+    /// Index of the root, built on first use and shared with every result
+    /// derived from this one.
+    pub fn document(&self) -> &Document {
+        self.document.get_or_init(|| Document::new(&*self.root))
+    }
     pub fn len(&self) -> usize {
         self.nodes.len()
     }
-    pub fn iter(&self) -> std::slice::Iter<Node<T>> {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    pub fn locations(&self) -> Vec<Option<Location>> {
+        self.iter().map(|node| node.location).collect()
+    }
+    pub fn texts(&self) -> Vec<String> {
+        self.texts_with(Render::Tokens)
+    }
+    pub fn texts_with(&self, render: Render) -> Vec<String> {
+        self.iter().map(|node| node.render(render)).collect()
+    }
+    /// One `compile_error!(message)` per node, pointing at it.
+    pub fn error(&self, message: &str) -> TokenStream {
+        self.iter().map(|node| node.error(message)).collect()
+    }
+    pub fn node_paths(&self) -> Vec<Option<NodePath>> {
+        let document = self.document();
+        self.iter()
+            .map(|node| document.id(&node.path).map(|id| document.node_path(id)))
+            .collect()
+    }
+    pub fn resolve(&self, path: &NodePath) -> Option<Node<DynNode>> {
+        let id = self.document().resolve(path)?;
+        self.entries(Some(id).into_iter().collect()).first()
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, Node<T>> {
         self.nodes.iter()
     }
-    pub fn iter_mut(&mut self) -> std::slice::IterMut<Node<T>> {
-        self.nodes.iter_mut()
+    /// Copies the nodes first when they are shared with another result.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Node<T>> {
+        Rc::make_mut(&mut self.nodes).nodes_mut().iter_mut()
+    }
+    /// The nodes, copied only when they are shared with another result.
+    fn into_nodes(self) -> Vec<Node<T>> {
+        Rc::try_unwrap(self.nodes).map_or_else(|nodes| (**nodes).clone(), Selection::into_nodes)
+    }
+    /// The same selection borrowing from this result, sharing its root and
+    /// `Document`.
+    pub fn as_borrowed(&self) -> borrowed::QueryResult<'_, T, R> {
+        borrowed::QueryResult {
+            nodes: self
+                .iter()
+                .map(|node| borrowed::Node {
+                    data: &node.data,
+                    path: node.path.to_owned(),
+                })
+                .collect(),
+            root: &self.root,
+            document: self.document.clone(),
+        }
     }
-    pub fn filter<P>(&self, predicate: P) -> QueryResult<T, R>
+    pub fn filter<P>(&self, mut predicate: P) -> QueryResult<T, R>
     where
         for<'r> P: FnMut(&'r Node<T>) -> bool,
     {
-        QueryResult::new(
-            self.to_owned().into_iter().filter(predicate).collect(),
-            self.root.to_owned(),
-        )
+        self.with_nodes(self.iter().filter(|node| predicate(node)).cloned().collect())
+    }
+    /// The nodes at `ids` that are `U`s, fetched from the root.
+    fn entries<U: Queryable>(&self, ids: BTreeSet<usize>) -> QueryResult<U, R> {
+        let document = self.document();
+        let paths = ids.into_iter().map(|id| document.path(id)).collect();
+        self.with_nodes(fetch(&*self.root, paths))
+    }
+    fn paths(&self) -> impl Iterator<Item = &Vec<i64>> {
+        self.iter().map(|node| &node.path)
     }
     pub fn parents<U: Queryable>(&self) -> QueryResult<U, R> {
-        use std::collections::BTreeSet;
-        let mut path_list = BTreeSet::new();
-        for item in self.iter() {
-            let mut path = Vec::new();
-            for path_node in item.path.iter() {
-                path_list.insert(path.to_owned());
-                path.push(path_node.to_owned());
-            }
-        }
-        self.root
-            .query()
-            .filter(|node| path_list.contains(&node.path))
+        self.entries(self.document().parents(self.paths()))
     }
     pub fn parent<U: Queryable>(&self) -> QueryResult<U, R> {
-        use std::collections::BTreeSet;
-        let mut path_list = BTreeSet::new();
-        for item in self.iter() {
-            let mut path = item.path.to_owned();
-            if path.pop().is_some() {
-                path_list.insert(path);
-            }
-        }
-        self.root
-            .query()
-            .filter(|node| path_list.contains(&node.path))
+        self.entries(self.document().parent(self.paths()))
     }
-    pub fn prev<U: Queryable>(&self) -> QueryResult<U, R> {
-        use std::collections::BTreeSet;
-        let mut path_list = BTreeSet::new();
-        for item in self.iter() {
-            let mut path = item.path.to_owned();
-            let mut will_insert = false;
-            if let Some(last) = path.last_mut() {
-                if *last > 0 {
-                    *last -= 1;
-                    will_insert = true;
+    /// Nearest ancestor-or-self of type `U` of each node.
+    pub fn closest<U: Queryable>(&self) -> QueryResult<U, R> {
+        self.entries(self.document().closest(self.paths(), true, is::<U>()))
+    }
+    pub fn closest_any(&self, kinds: &[NodeKind]) -> QueryResult<DynNode, R> {
+        self.entries(
+            self.document()
+                .closest(self.paths(), true, |entry| kinds.contains(&entry.kind)),
+        )
+    }
+    /// Nearest enclosing function, closure, impl, trait or module of each
+    /// node, excluding the node itself.
+    pub fn offset_parent(&self) -> QueryResult<DynNode, R> {
+        self.entries(
+            self.document()
+                .closest(self.paths(), false, |entry| SCOPES.contains(&entry.kind)),
+        )
+    }
+    /// Ancestors of type `U` of each node below its nearest ancestor of
+    /// type `P`, or up to the root when there is none.
+    pub fn parents_until<U: Queryable, P: Queryable>(&self) -> QueryResult<U, R> {
+        let document = self.document();
+        let (wanted, stop) = (is::<U>(), is::<P>());
+        let mut ids = BTreeSet::new();
+        for id in document.ids(self.paths()) {
+            let mut parent = document[id].parent;
+            while let Some(id) = parent {
+                if stop(&document[id]) {
+                    break;
+                }
+                if wanted(&document[id]) {
+                    ids.insert(id);
                 }
+                parent = document[id].parent;
             }
-            if will_insert {
-                path_list.insert(path);
-            };
         }
-        self.root
-            .query()
-            .filter(|node| path_list.contains(&node.path))
+        self.entries(ids)
+    }
+    pub fn prev<U: Queryable>(&self) -> QueryResult<U, R> {
+        self.entries(self.document().prev(self.paths()))
     }
     pub fn prev_all<U: Queryable>(&self) -> QueryResult<U, R> {
-        use std::collections::HashMap;
-        let mut map = HashMap::new();
-        for item in self.iter() {
-            let mut path = item.path.to_owned();
-            if let Some(last) = path.pop() {
-                if map.get(&path).map_or(true, |value| (*value) > last) {
-                    map.insert(path, last);
-                }
-            }
-        }
-        self.root.query().filter(|node| {
-            let mut path = node.path.to_owned();
-            if let Some(last) = path.pop() {
-                let value = map.get(&path);
-                return value.map_or(false, |value| (*value) > last);
-            }
-            false
-        })
+        self.entries(self.document().prev_all(self.paths()))
     }
     pub fn prev_until<U: Queryable, P>(&self, predicate: P) -> QueryResult<U, R>
     where
         for<'r> P: FnMut(&'r Node<U>) -> bool,
     {
-        use std::collections::HashMap;
         let all: QueryResult<U, R> = self.prev_all();
-        let unitl = all.filter(predicate);
-        let mut map = HashMap::new();
-        for item in unitl {
-            let mut path = item.path;
-            if let Some(last) = path.pop() {
-                if map.get(&path).map_or(true, |value| (*value) > last) {
-                    map.insert(path, last);
-                }
-            }
-        }
-        all.filter(|node| {
-            let mut path = node.path.to_owned();
-            if let Some(last) = path.pop() {
-                return map.get(&path).map_or(true, |value| (*value) < last);
-            }
-            false
-        })
+        let until = Axis::prev_until(all.filter(predicate).paths());
+        self.with_nodes(
+            all.into_nodes()
+                .into_iter()
+                .filter(|node| until.contains(&node.path))
+                .collect(),
+        )
     }
     pub fn next<U: Queryable>(&self) -> QueryResult<U, R> {
-        use std::collections::BTreeSet;
-        let mut path_list = BTreeSet::new();
-        for item in self.to_owned().into_iter() {
-            let mut path = item.path.to_owned();
-            let mut will_insert = false;
-            if let Some(last) = path.last_mut() {
-                *last += 1;
-                will_insert = true;
-            }
-            if will_insert {
-                path_list.insert(path);
-            };
-        }
-        self.root
-            .query()
-            .filter(|node| path_list.contains(&node.path))
+        self.entries(self.document().next(self.paths()))
     }
     pub fn next_all<U: Queryable>(&self) -> QueryResult<U, R> {
-        use std::collections::HashMap;
-        let mut map = HashMap::new();
-        for item in self.iter() {
-            let mut path = item.path.to_owned();
-            if let Some(last) = path.pop() {
-                if map.get(&path).map_or(true, |value| (*value) < last) {
-                    map.insert(path, last);
-                }
-            }
-        }
-        self.root.query().filter(|node| {
-            let mut path = node.path.to_owned();
-            if let Some(last) = path.pop() {
-                let value = map.get(&path);
-                return value.map_or(false, |value| (*value) < last);
-            }
-            false
-        })
+        self.entries(self.document().next_all(self.paths()))
     }
     pub fn next_until<U: Queryable, P>(&self, predicate: P) -> QueryResult<U, R>
     where
         for<'r> P: FnMut(&'r Node<U>) -> bool,
     {
-        use std::collections::HashMap;
         let all: QueryResult<U, R> = self.next_all();
-        let unitl = all.filter(predicate);
-        let mut map = HashMap::new();
-        for item in unitl {
-            let mut path = item.path;
-            if let Some(last) = path.pop() {
-                if map.get(&path).map_or(true, |value| (*value) < last) {
-                    map.insert(path, last);
-                }
-            }
-        }
-        all.filter(|node| {
-            let mut path = node.path.to_owned();
-            if let Some(last) = path.pop() {
-                return map.get(&path).map_or(true, |value| (*value) > last);
-            }
-            false
-        })
+        let until = Axis::next_until(all.filter(predicate).paths());
+        self.with_nodes(
+            all.into_nodes()
+                .into_iter()
+                .filter(|node| until.contains(&node.path))
+                .collect(),
+        )
     }
     pub fn siblings<U: Queryable>(&self) -> QueryResult<U, R> {
-        use std::collections::HashMap;
-        let mut map = HashMap::<_, Option<i64>>::new();
-        for node in self.nodes.to_owned().into_iter() {
-            let mut path = node.path;
-            if let Some(last) = path.pop().to_owned() {
-                match map.get(&path).map(|item| *item) {
-                    None => {
-                        map.insert(path, Some(last.to_owned()));
-                    }
-                    Some(item) => {
-                        if item.map_or(false, |item| item != last) {
-                            map.insert(path, None);
-                        }
-                    }
-                }
-            }
-        }
-        self.root.find().filter(|node| {
-            let mut path = node.path.to_owned();
-            if let Some(last) = path.pop() {
-                return map.get(&path)
-                    .map_or(false, |value| value.map_or(true, |value| value != last));
-            }
-            false
-        })
+        self.entries(self.document().siblings(self.paths()))
     }
     pub fn eq(&self, index: isize) -> Option<Node<T>> {
-        let id = if index >= 0 {
-            index as usize
-        } else {
-            let offset = -index as usize;
-            if offset > self.len() {
-                return None;
-            }
-            self.len() - offset
-        };
-        self.nodes.get(id).map(|node| node.to_owned())
+        if index >= 0 {
+            let mut skipped = 0;
+            return self.nodes.find(|_| {
+                skipped += 1;
+                skipped > index as usize
+            });
+        }
+        let offset = -index as usize;
+        if offset > self.len() {
+            return None;
+        }
+        self.nodes
+            .get(self.len() - offset)
+            .map(|node| node.to_owned())
     }
     pub fn first(&self) -> Option<Node<T>> {
-        self.nodes.first().map(|node| node.to_owned())
+        self.nodes.find(|_| true)
     }
     pub fn last(&self) -> Option<Node<T>> {
         self.nodes.last().map(|node| node.to_owned())
@@ -354,16 +617,16 @@ impl<T: Queryable, R: Queryable> QueryResult<T, R> {
     where
         F: FnMut(Node<T>) -> B,
     {
-        self.nodes.to_owned().into_iter().map(f).collect()
+        self.nodes.iter().cloned().map(f).collect()
     }
     pub fn is<F>(&self, f: F) -> bool
     where
         for<'r> F: FnMut(&'r Node<T>) -> bool,
     {
-        self.iter().any(f)
+        self.nodes.find(f).is_some()
     }
     pub fn has(&self) -> bool {
-        self.len() > 0
+        self.first().is_some()
     }
     pub fn not<P>(&self, mut predicate: P) -> QueryResult<T, R>
     where
@@ -371,18 +634,265 @@ impl<T: Queryable, R: Queryable> QueryResult<T, R> {
     {
         self.filter(|p: &Node<T>| !(predicate(p)))
     }
+    /// Keeps the nodes with a descendant of type `U`.
+    pub fn has_descendant<U: Queryable>(&self) -> QueryResult<T, R> {
+        self.has_descendant_matching::<U, _>(|_| true)
+    }
+    pub fn has_descendant_matching<U: Queryable, P>(&self, mut predicate: P) -> QueryResult<T, R>
+    where
+        for<'r> P: FnMut(&'r Node<U>) -> bool,
+    {
+        self.filter(|node| {
+            node.data
+                .visit::<U>(node.path.to_owned(), None)
+                .iter()
+                .any(|inner| inner.path != node.path && predicate(inner))
+        })
+    }
+    /// Keeps the nodes that are or contain the identifier `name`.
+    pub fn contains_ident(&self, name: &str) -> QueryResult<T, R> {
+        self.filter(|node| {
+            node.data
+                .visit::<Ident>(Vec::new(), None)
+                .iter()
+                .any(|ident| ident.data == name)
+        })
+    }
+    /// Keeps the nodes whose code, as printed by `Node::to_source`,
+    /// contains `text`.
+    pub fn contains_text(&self, text: &str) -> QueryResult<T, R> {
+        self.filter(|node| node.to_source().contains(text))
+    }
+    /// Keeps the nodes with an attribute named `name`, such as `serde` for
+    /// `#[serde(rename = "id")]`.
+    pub fn with_attribute(&self, name: &str) -> QueryResult<T, R> {
+        self.filter(|node| {
+            attrs::own::<Attribute, _>(&node.data, &node.path)
+                .iter()
+                .any(|attr| attrs::name(&attr.data.path) == name)
+        })
+    }
+    /// The literals given to `key` in the `name` attributes of the nodes,
+    /// `"id"` for `attribute_value("serde", "rename")` on a field with
+    /// `#[serde(rename = "id")]`.
+    pub fn attribute_value(&self, name: &str, key: &str) -> QueryResult<Lit, R> {
+        let metas = self.metas();
+        self.with_nodes(
+            metas
+                .iter()
+                .filter(|meta| meta.data.name() == name)
+                .flat_map(|meta| attrs::values(meta, key))
+                .collect(),
+        )
+    }
+    /// The nodes of type `U`, such as `Meta` or `NestedMeta`, in the parsed
+    /// attributes of the nodes. Their paths extend the path of the attribute
+    /// they come from, see `ATTR_META`; attributes that are not meta items
+    /// are skipped.
+    pub fn attrs<U: Queryable>(&self) -> QueryResult<U, R> {
+        let metas = self.metas();
+        self.with_nodes(
+            metas
+                .iter()
+                .flat_map(|meta| meta.data.visit(meta.path.to_owned(), None))
+                .collect(),
+        )
+    }
+    /// The documentation of each node, outer then inner.
+    pub fn doc_comments(&self) -> Vec<DocComment> {
+        self.iter()
+            .flat_map(|node| doc::comments(&node.to_dyn()))
+            .collect()
+    }
+    /// The nodes of type `U` that are public and have no outer
+    /// documentation. A node is public when it is declared `pub` or has no
+    /// visibility of its own, like trait items and enum variants, and is
+    /// neither in a block nor in a node that is not `pub`.
+    pub fn undocumented<U: Queryable>(&self) -> QueryResult<U, R> {
+        let hidden = doc::hidden(&*self.root);
+        let nodes = self.query::<U>();
+        self.with_nodes(
+            nodes
+                .into_nodes()
+                .into_iter()
+                .filter(|node| {
+                    let inside = hidden
+                        .iter()
+                        .any(|path| node.path.len() > path.len() && node.path.starts_with(path));
+                    let public = attrs::own::<Visibility, _>(&node.data, &node.path)
+                        .iter()
+                        .all(|vis| matches!(vis.data, Visibility::Public(_)));
+                    !inside
+                        && public
+                        && doc::comments(&node.to_dyn())
+                            .iter()
+                            .all(|comment| comment.style != AttrStyle::Outer)
+                })
+                .collect(),
+        )
+    }
+    fn metas(&self) -> Vec<Node<Meta>> {
+        self.iter()
+            .flat_map(|node| attrs::own::<Attribute, _>(&node.data, &node.path))
+            .filter_map(|attr| attrs::meta(&attr))
+            .collect()
+    }
+    /// Sorts the nodes in document order and drops duplicates.
+    pub fn unique(&self) -> QueryResult<T, R> {
+        self.with_nodes(set::combine(&self.nodes, &[], path_of, |_, _| true))
+    }
+    /// Whether `other` was queried from this root: derived from the same
+    /// result or sharing its root, which is checked first, or else parsed
+    /// into a root of the same kind printing the same tokens. Roots that
+    /// print no tokens are `RootMismatch::Incomparable` unless shared.
+    pub fn same_root(&self, other: &QueryResult<T, R>) -> Result<(), RootMismatch> {
+        if Rc::ptr_eq(&self.document, &other.document) || Rc::ptr_eq(&self.root, &other.root) {
+            return Ok(());
+        }
+        if self.root.kind() != other.root.kind() {
+            return Err(RootMismatch::Different);
+        }
+        match (self.root.tokens(), other.root.tokens()) {
+            (Some(left), Some(right)) if left.to_string() == right.to_string() => Ok(()),
+            (Some(_), Some(_)) => Err(RootMismatch::Different),
+            _ => Err(RootMismatch::Incomparable),
+        }
+    }
+    pub fn union(&self, other: &QueryResult<T, R>) -> Result<QueryResult<T, R>, RootMismatch> {
+        self.combine(other, |left, right| left || right)
+    }
+    pub fn intersection(
+        &self,
+        other: &QueryResult<T, R>,
+    ) -> Result<QueryResult<T, R>, RootMismatch> {
+        self.combine(other, |left, right| left && right)
+    }
+    pub fn difference(&self, other: &QueryResult<T, R>) -> Result<QueryResult<T, R>, RootMismatch> {
+        self.combine(other, |left, right| left && !right)
+    }
+    pub fn symmetric_difference(
+        &self,
+        other: &QueryResult<T, R>,
+    ) -> Result<QueryResult<T, R>, RootMismatch> {
+        self.combine(other, |left, right| left != right)
+    }
+    fn combine<P>(&self, other: &QueryResult<T, R>, keep: P) -> Result<QueryResult<T, R>, RootMismatch>
+    where
+        P: Fn(bool, bool) -> bool,
+    {
+        self.same_root(other)?;
+        Ok(self.with_nodes(set::combine(&self.nodes, &other.nodes, path_of, keep)))
+    }
+    /// The identifier `ident` refers to: the name bound by a `let`, a
+    /// parameter, a pattern or a generic parameter, or an item declared in
+    /// an enclosing block or module. A definition is its own definition.
+    pub fn definition_of(&self, ident: &Node<Ident>) -> Option<Node<Ident>> {
+        let document = self.document();
+        let id = document.id(&ident.path)?;
+        let definition = document.resolution(&*self.root).definition(id)?;
+        self.entries(Some(definition).into_iter().collect())
+            .first()
+    }
+    /// The identifiers referring to the same definition as `ident`, other
+    /// than the definition itself.
+    pub fn references_of(&self, ident: &Node<Ident>) -> QueryResult<Ident, R> {
+        let document = self.document();
+        let references = match self.definition_of(ident) {
+            Some(definition) => document
+                .resolution(&*self.root)
+                .references(document.id(&definition.path).unwrap_or_default()),
+            None => Vec::new(),
+        };
+        self.entries(references.into_iter().collect())
+    }
+    pub fn query_dyn(&self) -> QueryResult<DynNode, R> {
+        self.query()
+    }
+    pub fn query_any(&self, kinds: &[NodeKind]) -> QueryResult<DynNode, R> {
+        let mut result = BTreeSet::new();
+        for i in self.iter() {
+            for j in i.data.visit_kinds(i.path.to_owned(), None, Some(kinds)) {
+                result.insert(j);
+            }
+        }
+        self.with_nodes(result.into_iter().collect())
+    }
+    pub fn find_kind(&self, kind: NodeKind) -> QueryResult<DynNode, R> {
+        self.query_any(&[kind])
+    }
+    pub fn select(&self, selector: &str) -> Result<QueryResult<DynNode, R>, SelectorError> {
+        selector
+            .parse::<Selector>()
+            .map(|selector| selector.apply(self))
+    }
+    pub fn matches<P: Queryable + Synom>(&self, pattern: &str) -> Result<Vec<Match<P>>, PatternError> {
+        Pattern::parse(pattern).map(|pattern| pattern.find(self))
+    }
+    pub fn query_pattern<P: Queryable + Synom>(
+        &self,
+        pattern: &str,
+    ) -> Result<QueryResult<P, R>, PatternError> {
+        self.matches(pattern)
+            .map(|matches| self.with_nodes(matches.into_iter().map(|m| m.node).collect()))
+    }
+}
+
+impl<R: Queryable> QueryResult<DynNode, R> {
+    pub fn downcast<U: Queryable>(&self) -> QueryResult<U, R> {
+        self.with_nodes(self.iter().filter_map(|node| node.downcast()).collect())
+    }
 }
 
 impl<T: Queryable, R: Queryable> IntoIterator for QueryResult<T, R> {
     type Item = Node<T>;
     type IntoIter = ::std::vec::IntoIter<Node<T>>;
     fn into_iter(self) -> Self::IntoIter {
-        self.nodes.into_iter()
+        self.into_nodes().into_iter()
     }
 }
 
-pub trait Queryable: Sized + 'static + Clone {
-    fn visit<U: Queryable>(&self, base: Vec<i64>, deep: Option<usize>) -> Vec<Node<U>>;
+mod sealed {
+    /// Implemented by the syntax types, `DynNode` and `DocComment`, the
+    /// only nodes the walks behind `Queryable` know how to visit.
+    pub trait Sealed {}
+}
+
+/// A node that can be queried: one of the syntax types, `DynNode` or
+/// `DocComment`. The trait is sealed, so it cannot be implemented outside
+/// this crate.
+pub trait Queryable: Sized + 'static + Clone + sealed::Sealed {
+    fn visit_kinds<U: Queryable>(
+        &self,
+        base: Vec<i64>,
+        deep: Option<usize>,
+        kinds: Option<&[NodeKind]>,
+    ) -> Vec<Node<U>>;
+    fn visit<U: Queryable>(&self, base: Vec<i64>, deep: Option<usize>) -> Vec<Node<U>> {
+        self.visit_kinds(base, deep, None)
+    }
+    /// Visits only the nodes that come after the relative path `after` in
+    /// document order, skipping the subtrees before it, and stops once
+    /// `limit` nodes are found.
+    fn visit_after<U: Queryable>(
+        &self,
+        base: Vec<i64>,
+        deep: Option<usize>,
+        after: Option<&[i64]>,
+        limit: Option<usize>,
+    ) -> Vec<Node<U>>;
+    fn visit_ref<'ast, U: Queryable>(
+        &'ast self,
+        base: Vec<i64>,
+        deep: Option<usize>,
+    ) -> Vec<borrowed::Node<'ast, U>>;
+    fn to_dyn(&self) -> DynNode;
+    fn kind(&self) -> NodeKind;
+    fn location(&self) -> Option<Location>;
+    /// `None` for the kinds syn does not print, such as `Span`.
+    fn tokens(&self) -> Option<TokenStream>;
+    fn downcast_from<N: Queryable>(node: &N) -> Option<Self> {
+        (node as &dyn Any).downcast_ref::<Self>().cloned()
+    }
     fn query<U: Queryable>(&self) -> QueryResult<U, Self> {
         query::<_, _>(self.to_owned())
     }
@@ -392,29 +902,361 @@ pub trait Queryable: Sized + 'static + Clone {
     fn children<U: Queryable>(&self) -> QueryResult<U, Self> {
         children::<_, _>(self.to_owned())
     }
+    fn query_dyn(&self) -> QueryResult<DynNode, Self> {
+        self.query()
+    }
+    fn query_any(&self, kinds: &[NodeKind]) -> QueryResult<DynNode, Self> {
+        QueryResult::new(self.visit_kinds(Vec::new(), None, Some(kinds)), self.to_owned())
+    }
+    fn find_kind(&self, kind: NodeKind) -> QueryResult<DynNode, Self> {
+        self.query_any(&[kind])
+    }
+    fn select(&self, selector: &str) -> Result<QueryResult<DynNode, Self>, SelectorError> {
+        select(self.to_owned(), selector)
+    }
+    fn matches<P: Queryable + Synom>(&self, pattern: &str) -> Result<Vec<Match<P>>, PatternError> {
+        root(self.to_owned()).matches(pattern)
+    }
+    fn query_pattern<P: Queryable + Synom>(
+        &self,
+        pattern: &str,
+    ) -> Result<QueryResult<P, Self>, PatternError> {
+        root(self.to_owned()).query_pattern(pattern)
+    }
+    fn query_macros<U: Queryable>(&self) -> QueryResult<U, Self> {
+        QueryResult::new(macros::visit(self, Vec::new()), self.to_owned())
+    }
+    fn undocumented<U: Queryable>(&self) -> QueryResult<U, Self> {
+        root(self.to_owned()).undocumented()
+    }
+    fn query_iter<U: Queryable>(&self) -> QueryIter<'_, U, Self> {
+        QueryIter::new(self, Vec::new())
+    }
+    fn query_ref<U: Queryable>(&self) -> borrowed::QueryResult<'_, U, Self> {
+        borrowed::QueryResult::new(self.visit_ref(Vec::new(), None), self)
+    }
+    fn find_ref<U: Queryable>(&self) -> borrowed::QueryResult<'_, U, Self> {
+        self.query_ref()
+    }
+    fn children_ref<U: Queryable>(&self) -> borrowed::QueryResult<'_, U, Self> {
+        borrowed::QueryResult::new(self.visit_ref(Vec::new(), Some(1)), self)
+    }
+}
+
+macro_rules! token_range {
+    ($struct_name:ident) => {
+        impl TokenRange for $struct_name {
+            fn token_range(&self) -> Option<(Span, Span)> {
+                tokens_range(self)
+            }
+        }
+    };
+    ($struct_name:ident manual) => {};
+}
+
+macro_rules! to_tokens {
+    ($node:ident) => {
+        Some($node.into_token_stream())
+    };
+    ($node:ident manual) => {{
+        let _ = $node;
+        None
+    }};
 }
 
 macro_rules! build_visit {
-    ($( $struct_name:ident:$fn_name:ident ),*) => (
+    ($( $struct_name:ident:$fn_name:ident $fn_mut:ident $($tokens:ident)* ),*) => (
 
         $(
+            impl sealed::Sealed for $struct_name {}
+
             impl Queryable for $struct_name {
-                fn visit<U: Queryable >(&self, base: Vec<i64>,deep:Option<usize>) -> Vec<Node<U>> {
-                    let mut query = Query::new(base,deep);
+                fn visit_kinds<U: Queryable>(
+                    &self,
+                    base: Vec<i64>,
+                    deep: Option<usize>,
+                    kinds: Option<&[NodeKind]>,
+                ) -> Vec<Node<U>> {
+                    let mut query = Query::<Vec<Node<U>>>::new(base, deep, kinds);
+                    query. $fn_name (self);
+                    query.results
+                }
+                fn visit_after<U: Queryable>(
+                    &self,
+                    base: Vec<i64>,
+                    deep: Option<usize>,
+                    after: Option<&[i64]>,
+                    limit: Option<usize>,
+                ) -> Vec<Node<U>> {
+                    let mut query = Query::<Vec<Node<U>>>::new(base, deep, None);
+                    query.after = after.map(|after| after.to_vec());
+                    query.limit = limit;
                     query. $fn_name (self);
                     query.results
                 }
+                fn visit_ref<'ast, U: Queryable>(
+                    &'ast self,
+                    base: Vec<i64>,
+                    deep: Option<usize>,
+                ) -> Vec<borrowed::Node<'ast, U>> {
+                    let mut query = Query::<Vec<borrowed::Node<U>>>::new(base, deep, None);
+                    query. $fn_name (self);
+                    query.results
+                }
+                fn to_dyn(&self) -> DynNode {
+                    DynNode::$struct_name(self.clone())
+                }
+                fn kind(&self) -> NodeKind {
+                    NodeKind::$struct_name
+                }
+                fn location(&self) -> Option<Location> {
+                    TokenRange::token_range(self).map(|(start, end)| Location::new(start, end))
+                }
+                fn tokens(&self) -> Option<TokenStream> {
+                    let node = self;
+                    to_tokens!(node $($tokens)*)
+                }
+            }
+            token_range!($struct_name $($tokens)*);
+
+            impl Accept for $struct_name {
+                fn accept<'ast, V: visit::Visit<'ast>>(&'ast self, visitor: &mut V) {
+                    visitor.$fn_name(self)
+                }
             }
         )*
 
-        impl<'ast,T:Queryable+Clone+Any> visit::Visit<'ast> for Query<T> {
+        #[derive(Debug, Clone)]
+        #[allow(clippy::large_enum_variant)]
+        pub enum DynNode {
+            $( $struct_name($struct_name), )*
+            DocComment(Box<DocComment>),
+        }
+
+        impl DynNode {
+            pub fn kind(&self) -> NodeKind {
+                match *self {
+                    $( DynNode::$struct_name(_) => NodeKind::$struct_name, )*
+                    DynNode::DocComment(_) => NodeKind::DocComment,
+                }
+            }
+            pub fn name(&self) -> &'static str {
+                self.kind().name()
+            }
+            pub fn as_any(&self) -> &dyn Any {
+                match *self {
+                    $( DynNode::$struct_name(ref node) => node, )*
+                    DynNode::DocComment(ref node) => &**node,
+                }
+            }
+            pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+                self.as_any().downcast_ref::<T>()
+            }
+            pub fn downcast<T: Queryable>(&self) -> Option<T> {
+                match *self {
+                    $( DynNode::$struct_name(ref node) => T::downcast_from(node), )*
+                    DynNode::DocComment(ref node) => T::downcast_from(&**node),
+                }
+            }
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum NodeKind {
+            $( $struct_name, )*
+            DocComment,
+        }
+
+        const NODE_KINDS: &[NodeKind] = &[ $( NodeKind::$struct_name, )* NodeKind::DocComment ];
+
+        impl NodeKind {
+            pub fn all() -> &'static [NodeKind] {
+                NODE_KINDS
+            }
+            pub fn from_name(name: &str) -> Option<NodeKind> {
+                match name {
+                    $( stringify!($struct_name) => Some(NodeKind::$struct_name), )*
+                    "DocComment" => Some(NodeKind::DocComment),
+                    _ => None,
+                }
+            }
+            pub fn name(self) -> &'static str {
+                match self {
+                    $( NodeKind::$struct_name => stringify!($struct_name), )*
+                    NodeKind::DocComment => "DocComment",
+                }
+            }
+        }
+
+        /// The kind of the syntax type `T`, `None` for `DynNode`.
+        fn kind_of_type<T: Any>() -> Option<NodeKind> {
+            $(
+                if TypeId::of::<T>() == TypeId::of::<$struct_name>() {
+                    return Some(NodeKind::$struct_name);
+                }
+            )*
+            if TypeId::of::<T>() == TypeId::of::<DocComment>() {
+                return Some(NodeKind::DocComment);
+            }
+            None
+        }
+
+        pub fn kind_of(value: &dyn Any) -> Option<NodeKind> {
+            $(
+                if value.is::<$struct_name>() {
+                    return Some(NodeKind::$struct_name);
+                }
+            )*
+            if value.is::<DocComment>() {
+                return Some(NodeKind::DocComment);
+            }
+            None
+        }
+
+        impl sealed::Sealed for DynNode {}
+
+        impl Queryable for DynNode {
+            fn visit_kinds<U: Queryable>(
+                &self,
+                base: Vec<i64>,
+                deep: Option<usize>,
+                kinds: Option<&[NodeKind]>,
+            ) -> Vec<Node<U>> {
+                match *self {
+                    $( DynNode::$struct_name(ref node) => node.visit_kinds(base, deep, kinds), )*
+                    DynNode::DocComment(ref node) => node.visit_kinds(base, deep, kinds),
+                }
+            }
+            fn visit_after<U: Queryable>(
+                &self,
+                base: Vec<i64>,
+                deep: Option<usize>,
+                after: Option<&[i64]>,
+                limit: Option<usize>,
+            ) -> Vec<Node<U>> {
+                match *self {
+                    $( DynNode::$struct_name(ref node) => node.visit_after(base, deep, after, limit), )*
+                    DynNode::DocComment(ref node) => node.visit_after(base, deep, after, limit),
+                }
+            }
+            fn visit_ref<'ast, U: Queryable>(
+                &'ast self,
+                base: Vec<i64>,
+                deep: Option<usize>,
+            ) -> Vec<borrowed::Node<'ast, U>> {
+                match *self {
+                    $( DynNode::$struct_name(ref node) => node.visit_ref(base, deep), )*
+                    DynNode::DocComment(ref node) => node.visit_ref(base, deep),
+                }
+            }
+            fn to_dyn(&self) -> DynNode {
+                self.clone()
+            }
+            fn kind(&self) -> NodeKind {
+                DynNode::kind(self)
+            }
+            fn location(&self) -> Option<Location> {
+                match *self {
+                    $( DynNode::$struct_name(ref node) => node.location(), )*
+                    DynNode::DocComment(ref node) => node.location(),
+                }
+            }
+            fn tokens(&self) -> Option<TokenStream> {
+                match *self {
+                    $( DynNode::$struct_name(ref node) => node.tokens(), )*
+                    DynNode::DocComment(ref node) => node.tokens(),
+                }
+            }
+            fn downcast_from<N: Queryable>(node: &N) -> Option<DynNode> {
+                Some(node.to_dyn())
+            }
+        }
+
+        impl<V: Queryable> visit_mut::VisitMut for Mutate<V> {
+            $(
+                fn $fn_mut(&mut self, i: &mut $struct_name) {
+                    if self.enter(i) {
+                        visit_mut::$fn_mut(self, i);
+                        self.leave(i);
+                    }
+                    self.next();
+                }
+            )*
+        }
+
+        fn visit_root_mut<V: Queryable>(mutate: &mut Mutate<V>, node: &mut dyn Any) {
+            use syn::visit_mut::VisitMut;
+            if let Some(node) = node.downcast_mut::<DynNode>() {
+                match *node {
+                    $( DynNode::$struct_name(ref mut node) => mutate.$fn_mut(node), )*
+                    DynNode::DocComment(_) => {}
+                }
+                return;
+            }
+            $(
+                if let Some(node) = node.downcast_mut::<$struct_name>() {
+                    return mutate.$fn_mut(node);
+                }
+            )*
+        }
+
+        impl<'ast> visit::Visit<'ast> for Edges {
+            $(
+                fn $fn_name(&mut self, i: &'ast $struct_name) {
+                    self.enter(NodeKind::$struct_name);
+                    Schema::walk(i, self);
+                    self.leave();
+                }
+            )*
+        }
+
+        /// Visits a root of any type, looking through a `DynNode`.
+        fn accept_root<'ast, V: visit::Visit<'ast>>(visitor: &mut V, node: &'ast dyn Any) {
+            if let Some(node) = node.downcast_ref::<DynNode>() {
+                match *node {
+                    $( DynNode::$struct_name(ref node) => node.accept(visitor), )*
+                    DynNode::DocComment(_) => {}
+                }
+                return;
+            }
+            $(
+                if let Some(node) = node.downcast_ref::<$struct_name>() {
+                    return node.accept(visitor);
+                }
+            )*
+        }
+
+        /// Visits a node of `kind`, without trying every type in turn.
+        fn accept_kind<'ast, V: visit::Visit<'ast>>(
+            visitor: &mut V,
+            node: &'ast dyn Any,
+            kind: NodeKind,
+        ) {
+            match kind {
+                $(
+                    NodeKind::$struct_name => {
+                        if let Some(node) = node.downcast_ref::<$struct_name>() {
+                            node.accept(visitor);
+                        }
+                    }
+                )*
+                NodeKind::DocComment => {}
+            }
+        }
+
+        impl<'ast, C: Collect<'ast>> visit::Visit<'ast> for Query<C> {
             $(
                 fn $fn_name(&mut self, i: &'ast $struct_name) {
-                    let val=i as &Any;
-                    if let Some(result) = val.downcast_ref::<T>() {
-                        self.mk_result(result.to_owned());
+                    let position = self.position();
+                    if position == Position::After
+                        && self.accepts(NodeKind::$struct_name)
+                        && self.results.collect(i, &self.base, &self.path)
+                    {
+                        self.found += 1;
                     }
-                    if self.deep.is_none()||self.path.len()<self.deep.unwrap() {
+                    if (position == Position::After || position == Position::Ancestor)
+                        && self.position() != Position::Done
+                        && (self.deep.is_none()||self.path.len()<self.deep.unwrap()) {
+                    doc::visit(self, i);
                     self.path.push(0);
                     $fn_name(self, i);
                     self.path.pop();
@@ -429,202 +1271,214 @@ macro_rules! build_visit {
 }
 
 build_visit!(
-    Abi: visit_abi,
-    AngleBracketedGenericArguments: visit_angle_bracketed_generic_arguments,
-    ArgCaptured: visit_arg_captured,
-    ArgSelf: visit_arg_self,
-    ArgSelfRef: visit_arg_self_ref,
-    Arm: visit_arm,
-    AttrStyle: visit_attr_style,
-    Attribute: visit_attribute,
-    BareFnArg: visit_bare_fn_arg,
-    BareFnArgName: visit_bare_fn_arg_name,
-    BinOp: visit_bin_op,
-    Binding: visit_binding,
-    Block: visit_block,
-    BoundLifetimes: visit_bound_lifetimes,
-    ConstParam: visit_const_param,
-    Data: visit_data,
-    DataEnum: visit_data_enum,
-    DataStruct: visit_data_struct,
-    DataUnion: visit_data_union,
-    DeriveInput: visit_derive_input,
-    Expr: visit_expr,
-    ExprArray: visit_expr_array,
-    ExprAssign: visit_expr_assign,
-    ExprAssignOp: visit_expr_assign_op,
-    ExprBinary: visit_expr_binary,
-    ExprBlock: visit_expr_block,
-    ExprBox: visit_expr_box,
-    ExprBreak: visit_expr_break,
-    ExprCall: visit_expr_call,
-    ExprCast: visit_expr_cast,
-    ExprCatch: visit_expr_catch,
-    ExprClosure: visit_expr_closure,
-    ExprContinue: visit_expr_continue,
-    ExprField: visit_expr_field,
-    ExprForLoop: visit_expr_for_loop,
-    ExprGroup: visit_expr_group,
-    ExprIf: visit_expr_if,
-    ExprIfLet: visit_expr_if_let,
-    ExprInPlace: visit_expr_in_place,
-    ExprIndex: visit_expr_index,
-    ExprLit: visit_expr_lit,
-    ExprLoop: visit_expr_loop,
-    ExprMacro: visit_expr_macro,
-    ExprMatch: visit_expr_match,
-    ExprMethodCall: visit_expr_method_call,
-    ExprParen: visit_expr_paren,
-    ExprPath: visit_expr_path,
-    ExprRange: visit_expr_range,
-    ExprReference: visit_expr_reference,
-    ExprRepeat: visit_expr_repeat,
-    ExprReturn: visit_expr_return,
-    ExprStruct: visit_expr_struct,
-    ExprTry: visit_expr_try,
-    ExprTuple: visit_expr_tuple,
-    ExprType: visit_expr_type,
-    ExprUnary: visit_expr_unary,
-    ExprUnsafe: visit_expr_unsafe,
-    ExprVerbatim: visit_expr_verbatim,
-    ExprWhile: visit_expr_while,
-    ExprWhileLet: visit_expr_while_let,
-    ExprYield: visit_expr_yield,
-    Field: visit_field,
-    FieldPat: visit_field_pat,
-    FieldValue: visit_field_value,
-    Fields: visit_fields,
-    FieldsNamed: visit_fields_named,
-    FieldsUnnamed: visit_fields_unnamed,
-    File: visit_file,
-    FnArg: visit_fn_arg,
-    FnDecl: visit_fn_decl,
-    ForeignItem: visit_foreign_item,
-    ForeignItemFn: visit_foreign_item_fn,
-    ForeignItemStatic: visit_foreign_item_static,
-    ForeignItemType: visit_foreign_item_type,
-    ForeignItemVerbatim: visit_foreign_item_verbatim,
-    GenericArgument: visit_generic_argument,
-    GenericMethodArgument: visit_generic_method_argument,
-    GenericParam: visit_generic_param,
-    Generics: visit_generics,
-    Ident: visit_ident,
-    ImplItem: visit_impl_item,
-    ImplItemConst: visit_impl_item_const,
-    ImplItemMacro: visit_impl_item_macro,
-    ImplItemMethod: visit_impl_item_method,
-    ImplItemType: visit_impl_item_type,
-    ImplItemVerbatim: visit_impl_item_verbatim,
-    Index: visit_index,
-    Item: visit_item,
-    ItemConst: visit_item_const,
-    ItemEnum: visit_item_enum,
-    ItemExternCrate: visit_item_extern_crate,
-    ItemFn: visit_item_fn,
-    ItemForeignMod: visit_item_foreign_mod,
-    ItemImpl: visit_item_impl,
-    ItemMacro: visit_item_macro,
-    ItemMacro2: visit_item_macro2,
-    ItemMod: visit_item_mod,
-    ItemStatic: visit_item_static,
-    ItemStruct: visit_item_struct,
-    ItemTrait: visit_item_trait,
-    ItemType: visit_item_type,
-    ItemUnion: visit_item_union,
-    ItemUse: visit_item_use,
-    ItemVerbatim: visit_item_verbatim,
-    Label: visit_label,
-    Lifetime: visit_lifetime,
-    LifetimeDef: visit_lifetime_def,
-    Lit: visit_lit,
-    LitBool: visit_lit_bool,
-    LitByte: visit_lit_byte,
-    LitByteStr: visit_lit_byte_str,
-    LitChar: visit_lit_char,
-    LitFloat: visit_lit_float,
-    LitInt: visit_lit_int,
-    LitStr: visit_lit_str,
-    LitVerbatim: visit_lit_verbatim,
-    Local: visit_local,
-    Macro: visit_macro,
-    MacroDelimiter: visit_macro_delimiter,
-    Member: visit_member,
-    Meta: visit_meta,
-    MetaList: visit_meta_list,
-    MetaNameValue: visit_meta_name_value,
-    MethodSig: visit_method_sig,
-    MethodTurbofish: visit_method_turbofish,
-    NestedMeta: visit_nested_meta,
-    ParenthesizedGenericArguments: visit_parenthesized_generic_arguments,
-    Pat: visit_pat,
-    PatBox: visit_pat_box,
-    PatIdent: visit_pat_ident,
-    PatLit: visit_pat_lit,
-    PatMacro: visit_pat_macro,
-    PatPath: visit_pat_path,
-    PatRange: visit_pat_range,
-    PatRef: visit_pat_ref,
-    PatSlice: visit_pat_slice,
-    PatStruct: visit_pat_struct,
-    PatTuple: visit_pat_tuple,
-    PatTupleStruct: visit_pat_tuple_struct,
-    PatVerbatim: visit_pat_verbatim,
-    PatWild: visit_pat_wild,
-    Path: visit_path,
-    PathArguments: visit_path_arguments,
-    PathSegment: visit_path_segment,
-    PredicateEq: visit_predicate_eq,
-    PredicateLifetime: visit_predicate_lifetime,
-    PredicateType: visit_predicate_type,
-    QSelf: visit_qself,
-    RangeLimits: visit_range_limits,
-    ReturnType: visit_return_type,
-    Span: visit_span,
-    Stmt: visit_stmt,
-    TraitBound: visit_trait_bound,
-    TraitBoundModifier: visit_trait_bound_modifier,
-    TraitItem: visit_trait_item,
-    TraitItemConst: visit_trait_item_const,
-    TraitItemMacro: visit_trait_item_macro,
-    TraitItemMethod: visit_trait_item_method,
-    TraitItemType: visit_trait_item_type,
-    TraitItemVerbatim: visit_trait_item_verbatim,
-    Type: visit_type,
-    TypeArray: visit_type_array,
-    TypeBareFn: visit_type_bare_fn,
-    TypeGroup: visit_type_group,
-    TypeImplTrait: visit_type_impl_trait,
-    TypeInfer: visit_type_infer,
-    TypeMacro: visit_type_macro,
-    TypeNever: visit_type_never,
-    TypeParam: visit_type_param,
-    TypeParamBound: visit_type_param_bound,
-    TypeParen: visit_type_paren,
-    TypePath: visit_type_path,
-    TypePtr: visit_type_ptr,
-    TypeReference: visit_type_reference,
-    TypeSlice: visit_type_slice,
-    TypeTraitObject: visit_type_trait_object,
-    TypeTuple: visit_type_tuple,
-    TypeVerbatim: visit_type_verbatim,
-    UnOp: visit_un_op,
-    UseGlob: visit_use_glob,
-    UseGroup: visit_use_group,
-    UseName: visit_use_name,
-    UsePath: visit_use_path,
-    UseRename: visit_use_rename,
-    UseTree: visit_use_tree,
-    Variant: visit_variant,
-    VisCrate: visit_vis_crate,
-    VisPublic: visit_vis_public,
-    VisRestricted: visit_vis_restricted,
-    Visibility: visit_visibility,
-    WhereClause: visit_where_clause,
-    WherePredicate: visit_where_predicate
+    Abi: visit_abi visit_abi_mut,
+    AngleBracketedGenericArguments: visit_angle_bracketed_generic_arguments visit_angle_bracketed_generic_arguments_mut,
+    ArgCaptured: visit_arg_captured visit_arg_captured_mut,
+    ArgSelf: visit_arg_self visit_arg_self_mut,
+    ArgSelfRef: visit_arg_self_ref visit_arg_self_ref_mut,
+    Arm: visit_arm visit_arm_mut,
+    AttrStyle: visit_attr_style visit_attr_style_mut manual,
+    Attribute: visit_attribute visit_attribute_mut,
+    BareFnArg: visit_bare_fn_arg visit_bare_fn_arg_mut,
+    BareFnArgName: visit_bare_fn_arg_name visit_bare_fn_arg_name_mut,
+    BinOp: visit_bin_op visit_bin_op_mut,
+    Binding: visit_binding visit_binding_mut,
+    Block: visit_block visit_block_mut,
+    BoundLifetimes: visit_bound_lifetimes visit_bound_lifetimes_mut,
+    ConstParam: visit_const_param visit_const_param_mut,
+    Data: visit_data visit_data_mut manual,
+    DataEnum: visit_data_enum visit_data_enum_mut manual,
+    DataStruct: visit_data_struct visit_data_struct_mut manual,
+    DataUnion: visit_data_union visit_data_union_mut manual,
+    DeriveInput: visit_derive_input visit_derive_input_mut,
+    Expr: visit_expr visit_expr_mut,
+    ExprArray: visit_expr_array visit_expr_array_mut,
+    ExprAssign: visit_expr_assign visit_expr_assign_mut,
+    ExprAssignOp: visit_expr_assign_op visit_expr_assign_op_mut,
+    ExprBinary: visit_expr_binary visit_expr_binary_mut,
+    ExprBlock: visit_expr_block visit_expr_block_mut,
+    ExprBox: visit_expr_box visit_expr_box_mut,
+    ExprBreak: visit_expr_break visit_expr_break_mut,
+    ExprCall: visit_expr_call visit_expr_call_mut,
+    ExprCast: visit_expr_cast visit_expr_cast_mut,
+    ExprCatch: visit_expr_catch visit_expr_catch_mut,
+    ExprClosure: visit_expr_closure visit_expr_closure_mut,
+    ExprContinue: visit_expr_continue visit_expr_continue_mut,
+    ExprField: visit_expr_field visit_expr_field_mut,
+    ExprForLoop: visit_expr_for_loop visit_expr_for_loop_mut,
+    ExprGroup: visit_expr_group visit_expr_group_mut,
+    ExprIf: visit_expr_if visit_expr_if_mut,
+    ExprIfLet: visit_expr_if_let visit_expr_if_let_mut,
+    ExprInPlace: visit_expr_in_place visit_expr_in_place_mut,
+    ExprIndex: visit_expr_index visit_expr_index_mut,
+    ExprLit: visit_expr_lit visit_expr_lit_mut,
+    ExprLoop: visit_expr_loop visit_expr_loop_mut,
+    ExprMacro: visit_expr_macro visit_expr_macro_mut,
+    ExprMatch: visit_expr_match visit_expr_match_mut,
+    ExprMethodCall: visit_expr_method_call visit_expr_method_call_mut,
+    ExprParen: visit_expr_paren visit_expr_paren_mut,
+    ExprPath: visit_expr_path visit_expr_path_mut,
+    ExprRange: visit_expr_range visit_expr_range_mut,
+    ExprReference: visit_expr_reference visit_expr_reference_mut,
+    ExprRepeat: visit_expr_repeat visit_expr_repeat_mut,
+    ExprReturn: visit_expr_return visit_expr_return_mut,
+    ExprStruct: visit_expr_struct visit_expr_struct_mut,
+    ExprTry: visit_expr_try visit_expr_try_mut,
+    ExprTuple: visit_expr_tuple visit_expr_tuple_mut,
+    ExprType: visit_expr_type visit_expr_type_mut,
+    ExprUnary: visit_expr_unary visit_expr_unary_mut,
+    ExprUnsafe: visit_expr_unsafe visit_expr_unsafe_mut,
+    ExprVerbatim: visit_expr_verbatim visit_expr_verbatim_mut,
+    ExprWhile: visit_expr_while visit_expr_while_mut,
+    ExprWhileLet: visit_expr_while_let visit_expr_while_let_mut,
+    ExprYield: visit_expr_yield visit_expr_yield_mut,
+    Field: visit_field visit_field_mut,
+    FieldPat: visit_field_pat visit_field_pat_mut,
+    FieldValue: visit_field_value visit_field_value_mut,
+    Fields: visit_fields visit_fields_mut,
+    FieldsNamed: visit_fields_named visit_fields_named_mut,
+    FieldsUnnamed: visit_fields_unnamed visit_fields_unnamed_mut,
+    File: visit_file visit_file_mut,
+    FnArg: visit_fn_arg visit_fn_arg_mut,
+    FnDecl: visit_fn_decl visit_fn_decl_mut manual,
+    ForeignItem: visit_foreign_item visit_foreign_item_mut,
+    ForeignItemFn: visit_foreign_item_fn visit_foreign_item_fn_mut,
+    ForeignItemStatic: visit_foreign_item_static visit_foreign_item_static_mut,
+    ForeignItemType: visit_foreign_item_type visit_foreign_item_type_mut,
+    ForeignItemVerbatim: visit_foreign_item_verbatim visit_foreign_item_verbatim_mut,
+    GenericArgument: visit_generic_argument visit_generic_argument_mut,
+    GenericMethodArgument: visit_generic_method_argument visit_generic_method_argument_mut,
+    GenericParam: visit_generic_param visit_generic_param_mut,
+    Generics: visit_generics visit_generics_mut,
+    Ident: visit_ident visit_ident_mut,
+    ImplItem: visit_impl_item visit_impl_item_mut,
+    ImplItemConst: visit_impl_item_const visit_impl_item_const_mut,
+    ImplItemMacro: visit_impl_item_macro visit_impl_item_macro_mut,
+    ImplItemMethod: visit_impl_item_method visit_impl_item_method_mut,
+    ImplItemType: visit_impl_item_type visit_impl_item_type_mut,
+    ImplItemVerbatim: visit_impl_item_verbatim visit_impl_item_verbatim_mut,
+    Index: visit_index visit_index_mut,
+    Item: visit_item visit_item_mut,
+    ItemConst: visit_item_const visit_item_const_mut,
+    ItemEnum: visit_item_enum visit_item_enum_mut,
+    ItemExternCrate: visit_item_extern_crate visit_item_extern_crate_mut,
+    ItemFn: visit_item_fn visit_item_fn_mut,
+    ItemForeignMod: visit_item_foreign_mod visit_item_foreign_mod_mut,
+    ItemImpl: visit_item_impl visit_item_impl_mut,
+    ItemMacro: visit_item_macro visit_item_macro_mut,
+    ItemMacro2: visit_item_macro2 visit_item_macro2_mut,
+    ItemMod: visit_item_mod visit_item_mod_mut,
+    ItemStatic: visit_item_static visit_item_static_mut,
+    ItemStruct: visit_item_struct visit_item_struct_mut,
+    ItemTrait: visit_item_trait visit_item_trait_mut,
+    ItemType: visit_item_type visit_item_type_mut,
+    ItemUnion: visit_item_union visit_item_union_mut,
+    ItemUse: visit_item_use visit_item_use_mut,
+    ItemVerbatim: visit_item_verbatim visit_item_verbatim_mut,
+    Label: visit_label visit_label_mut,
+    Lifetime: visit_lifetime visit_lifetime_mut,
+    LifetimeDef: visit_lifetime_def visit_lifetime_def_mut,
+    Lit: visit_lit visit_lit_mut,
+    LitBool: visit_lit_bool visit_lit_bool_mut,
+    LitByte: visit_lit_byte visit_lit_byte_mut,
+    LitByteStr: visit_lit_byte_str visit_lit_byte_str_mut,
+    LitChar: visit_lit_char visit_lit_char_mut,
+    LitFloat: visit_lit_float visit_lit_float_mut,
+    LitInt: visit_lit_int visit_lit_int_mut,
+    LitStr: visit_lit_str visit_lit_str_mut,
+    LitVerbatim: visit_lit_verbatim visit_lit_verbatim_mut,
+    Local: visit_local visit_local_mut,
+    Macro: visit_macro visit_macro_mut,
+    MacroDelimiter: visit_macro_delimiter visit_macro_delimiter_mut manual,
+    Member: visit_member visit_member_mut,
+    Meta: visit_meta visit_meta_mut,
+    MetaList: visit_meta_list visit_meta_list_mut,
+    MetaNameValue: visit_meta_name_value visit_meta_name_value_mut,
+    MethodSig: visit_method_sig visit_method_sig_mut,
+    MethodTurbofish: visit_method_turbofish visit_method_turbofish_mut,
+    NestedMeta: visit_nested_meta visit_nested_meta_mut,
+    ParenthesizedGenericArguments: visit_parenthesized_generic_arguments visit_parenthesized_generic_arguments_mut,
+    Pat: visit_pat visit_pat_mut,
+    PatBox: visit_pat_box visit_pat_box_mut,
+    PatIdent: visit_pat_ident visit_pat_ident_mut,
+    PatLit: visit_pat_lit visit_pat_lit_mut,
+    PatMacro: visit_pat_macro visit_pat_macro_mut,
+    PatPath: visit_pat_path visit_pat_path_mut,
+    PatRange: visit_pat_range visit_pat_range_mut,
+    PatRef: visit_pat_ref visit_pat_ref_mut,
+    PatSlice: visit_pat_slice visit_pat_slice_mut,
+    PatStruct: visit_pat_struct visit_pat_struct_mut,
+    PatTuple: visit_pat_tuple visit_pat_tuple_mut,
+    PatTupleStruct: visit_pat_tuple_struct visit_pat_tuple_struct_mut,
+    PatVerbatim: visit_pat_verbatim visit_pat_verbatim_mut,
+    PatWild: visit_pat_wild visit_pat_wild_mut,
+    Path: visit_path visit_path_mut,
+    PathArguments: visit_path_arguments visit_path_arguments_mut,
+    PathSegment: visit_path_segment visit_path_segment_mut,
+    PredicateEq: visit_predicate_eq visit_predicate_eq_mut,
+    PredicateLifetime: visit_predicate_lifetime visit_predicate_lifetime_mut,
+    PredicateType: visit_predicate_type visit_predicate_type_mut,
+    QSelf: visit_qself visit_qself_mut manual,
+    RangeLimits: visit_range_limits visit_range_limits_mut manual,
+    ReturnType: visit_return_type visit_return_type_mut,
+    Span: visit_span visit_span_mut manual,
+    Stmt: visit_stmt visit_stmt_mut,
+    TraitBound: visit_trait_bound visit_trait_bound_mut,
+    TraitBoundModifier: visit_trait_bound_modifier visit_trait_bound_modifier_mut,
+    TraitItem: visit_trait_item visit_trait_item_mut,
+    TraitItemConst: visit_trait_item_const visit_trait_item_const_mut,
+    TraitItemMacro: visit_trait_item_macro visit_trait_item_macro_mut,
+    TraitItemMethod: visit_trait_item_method visit_trait_item_method_mut,
+    TraitItemType: visit_trait_item_type visit_trait_item_type_mut,
+    TraitItemVerbatim: visit_trait_item_verbatim visit_trait_item_verbatim_mut,
+    Type: visit_type visit_type_mut,
+    TypeArray: visit_type_array visit_type_array_mut,
+    TypeBareFn: visit_type_bare_fn visit_type_bare_fn_mut,
+    TypeGroup: visit_type_group visit_type_group_mut,
+    TypeImplTrait: visit_type_impl_trait visit_type_impl_trait_mut,
+    TypeInfer: visit_type_infer visit_type_infer_mut,
+    TypeMacro: visit_type_macro visit_type_macro_mut,
+    TypeNever: visit_type_never visit_type_never_mut,
+    TypeParam: visit_type_param visit_type_param_mut,
+    TypeParamBound: visit_type_param_bound visit_type_param_bound_mut,
+    TypeParen: visit_type_paren visit_type_paren_mut,
+    TypePath: visit_type_path visit_type_path_mut,
+    TypePtr: visit_type_ptr visit_type_ptr_mut,
+    TypeReference: visit_type_reference visit_type_reference_mut,
+    TypeSlice: visit_type_slice visit_type_slice_mut,
+    TypeTraitObject: visit_type_trait_object visit_type_trait_object_mut,
+    TypeTuple: visit_type_tuple visit_type_tuple_mut,
+    TypeVerbatim: visit_type_verbatim visit_type_verbatim_mut,
+    UnOp: visit_un_op visit_un_op_mut,
+    UseGlob: visit_use_glob visit_use_glob_mut,
+    UseGroup: visit_use_group visit_use_group_mut,
+    UseName: visit_use_name visit_use_name_mut,
+    UsePath: visit_use_path visit_use_path_mut,
+    UseRename: visit_use_rename visit_use_rename_mut,
+    UseTree: visit_use_tree visit_use_tree_mut,
+    Variant: visit_variant visit_variant_mut,
+    VisCrate: visit_vis_crate visit_vis_crate_mut,
+    VisPublic: visit_vis_public visit_vis_public_mut,
+    VisRestricted: visit_vis_restricted visit_vis_restricted_mut,
+    Visibility: visit_visibility visit_visibility_mut,
+    WhereClause: visit_where_clause visit_where_clause_mut,
+    WherePredicate: visit_where_predicate visit_where_predicate_mut
 );
 
+impl fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
 pub fn query<T: Queryable, U: Queryable>(i: U) -> QueryResult<T, U> {
-    QueryResult::new(i.visit(Vec::new(), None), i.to_owned())
+    let root = Rc::new(i);
+    QueryResult {
+        nodes: Rc::new(Selection::root(root.clone())),
+        root,
+        document: Rc::new(OnceCell::new()),
+        history: None,
+    }
 }
 
 pub fn find<T: Queryable, U: Queryable>(i: U) -> QueryResult<T, U> {
@@ -634,3 +1488,21 @@ pub fn find<T: Queryable, U: Queryable>(i: U) -> QueryResult<T, U> {
 pub fn children<T: Queryable, U: Queryable>(i: U) -> QueryResult<T, U> {
     QueryResult::new(i.visit(Vec::new(), Some(1)), i.to_owned())
 }
+
+pub fn select<U: Queryable>(
+    i: U,
+    selector: &str,
+) -> Result<QueryResult<DynNode, U>, SelectorError> {
+    root(i).select(selector)
+}
+
+fn root<U: Queryable>(i: U) -> QueryResult<U, U> {
+    QueryResult::new(
+        vec![Node {
+            data: i.to_owned(),
+            path: Vec::new(),
+            location: i.location(),
+        }],
+        i,
+    )
+}
@@ -0,0 +1,244 @@
+use attrs;
+use location::strip_bom;
+use mutate::WRAPPERS;
+use proc_macro2::{Delimiter, TokenTree};
+use std::collections::HashMap;
+use std::fmt;
+use syn::punctuated::Punctuated;
+use syn::synom::Parser;
+use syn::token::Comma;
+use syn::{Attribute, Path};
+use {Document, DynNode, Location, Node, NodeKind, Queryable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A check run by a `LintRunner` on the nodes of the kinds it queries.
+pub trait Lint {
+    /// The name `#[allow(...)]` takes to silence the lint.
+    fn name(&self) -> &'static str;
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+    /// The kinds of the nodes passed to `message`.
+    fn query(&self) -> Vec<NodeKind>;
+    /// What is wrong with `node`, `None` when nothing is.
+    fn message(&self, node: &Node<DynNode>) -> Option<String>;
+}
+
+/// A node reported by a lint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub lint: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Location>,
+    pub path: Vec<i64>,
+}
+
+impl Diagnostic {
+    /// The diagnostic as rustc prints it, with the offending line of
+    /// `source`, the text of `file`, underlined.
+    pub fn to_rustc(&self, file: &str, source: &str) -> String {
+        let mut out = format!("{}[{}]: {}\n", self.severity, self.lint, self.message);
+        let span = match self.span {
+            Some(span) if span.start.line > 0 => span,
+            _ => return out,
+        };
+        let line = strip_bom(source)
+            .lines()
+            .nth(span.start.line - 1)
+            .unwrap_or("");
+        let number = span.start.line.to_string();
+        let gutter = " ".repeat(number.len());
+        // Columns are byte offsets: the carets are placed by characters,
+        // keeping the tabs before them so they line up with the source.
+        let start = span.start.column.min(line.len());
+        let end = if span.end.line == span.start.line {
+            span.end.column.clamp(start, line.len())
+        } else {
+            line.len()
+        };
+        let indent: String = line
+            .get(..start)
+            .unwrap_or("")
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = line.get(start..end).map_or(0, |text| text.chars().count());
+        out.push_str(&format!("{}--> {}:{}\n", gutter, file, span));
+        out.push_str(&format!("{} |\n", gutter));
+        out.push_str(&format!("{} | {}\n", number, line));
+        out.push_str(&format!(
+            "{} | {}{}\n",
+            gutter,
+            indent,
+            "^".repeat(width.max(1))
+        ));
+        out
+    }
+    /// The diagnostic as a JSON object on one line.
+    pub fn to_json(&self, file: &str) -> String {
+        let position = match self.span {
+            Some(span) => format!(
+                "\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}",
+                span.start.line,
+                span.start.column + 1,
+                span.end.line,
+                span.end.column + 1
+            ),
+            None => {
+                "\"line\":null,\"column\":null,\"end_line\":null,\"end_column\":null".to_owned()
+            }
+        };
+        let path: Vec<String> = self.path.iter().map(|index| index.to_string()).collect();
+        format!(
+            "{{\"lint\":{},\"severity\":{},\"message\":{},\"file\":{},{},\"path\":[{}]}}",
+            json_string(self.lint),
+            json_string(&self.severity.to_string()),
+            json_string(&self.message),
+            json_string(file),
+            position,
+            path.join(",")
+        )
+    }
+}
+
+/// `text` as a JSON string, quotes included; shared with the `synq` binary.
+#[doc(hidden)]
+pub fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// The lints named by `#[allow(...)]`, such as `unused` or `tool::lint`.
+fn allowed(attr: &Attribute) -> Vec<String> {
+    if attrs::name(&attr.path) != "allow" {
+        return Vec::new();
+    }
+    let mut tts = attr.tts.clone().into_iter();
+    let list = match (tts.next(), tts.next()) {
+        (Some(TokenTree::Group(ref group)), None)
+            if group.delimiter() == Delimiter::Parenthesis =>
+        {
+            group.stream()
+        }
+        _ => return Vec::new(),
+    };
+    Punctuated::<Path, Comma>::parse_terminated
+        .parse2(list)
+        .map(|paths| paths.iter().map(attrs::name).collect())
+        .unwrap_or_default()
+}
+
+/// The lints allowed by the attributes of each node, by id. The attributes
+/// of a node wrapped in one of the `WRAPPERS`, as an `Item` wraps an
+/// `ItemStruct`, also count for the wrapper.
+fn allows<R: Queryable>(root: &R, document: &Document) -> HashMap<usize, Vec<String>> {
+    let mut allows = HashMap::new();
+    for attr in root.visit::<Attribute>(Vec::new(), None) {
+        let names = allowed(&attr.data);
+        if names.is_empty() {
+            continue;
+        }
+        let mut owner = document.id(&attr.path).and_then(|id| document[id].parent);
+        while let Some(id) = owner {
+            allows
+                .entry(id)
+                .or_insert_with(Vec::new)
+                .extend(names.iter().cloned());
+            owner = document[id]
+                .parent
+                .filter(|&parent| WRAPPERS.contains(&document[parent].kind));
+        }
+    }
+    allows
+}
+
+/// Runs lints over a root in a single traversal.
+#[derive(Default)]
+pub struct LintRunner {
+    lints: Vec<Box<dyn Lint>>,
+}
+
+impl LintRunner {
+    pub fn new() -> LintRunner {
+        LintRunner::default()
+    }
+    pub fn add<L: Lint + 'static>(&mut self, lint: L) -> &mut LintRunner {
+        self.lints.push(Box::new(lint));
+        self
+    }
+    /// The diagnostics of every lint in document order, leaving out those
+    /// on or inside a node with `#[allow(name)]`.
+    pub fn run<R: Queryable>(&self, root: &R) -> Vec<Diagnostic> {
+        let queries: Vec<Vec<NodeKind>> = self.lints.iter().map(|lint| lint.query()).collect();
+        let mut kinds: Vec<NodeKind> = queries.iter().flatten().cloned().collect();
+        kinds.sort();
+        kinds.dedup();
+        let nodes = root.query_any(&kinds);
+        let document = nodes.document();
+        let allows = allows(root, document);
+        let mut diagnostics = Vec::new();
+        for node in nodes.iter() {
+            for (lint, query) in self.lints.iter().zip(&queries) {
+                if !query.contains(&node.kind()) {
+                    continue;
+                }
+                let message = match lint.message(node) {
+                    Some(message) => message,
+                    None => continue,
+                };
+                let mut id = document.id(&node.path);
+                let mut allowed = false;
+                while let Some(ancestor) = id {
+                    allowed |= allows
+                        .get(&ancestor)
+                        .is_some_and(|names| names.iter().any(|name| name == lint.name()));
+                    id = document[ancestor].parent;
+                }
+                if !allowed {
+                    diagnostics.push(Diagnostic {
+                        lint: lint.name(),
+                        severity: lint.severity(),
+                        message,
+                        span: node.location,
+                        path: node.path.to_owned(),
+                    });
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+impl fmt::Debug for LintRunner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = self.lints.iter().map(|lint| lint.name()).collect();
+        f.debug_struct("LintRunner").field("lints", &names).finish()
+    }
+}
@@ -0,0 +1,187 @@
+use proc_macro2::{LineColumn, Span};
+use quote::ToTokens;
+use std::fmt;
+use std::ops::Range;
+use syn::*;
+
+/// Source location of a node, taken from the spans of its first and last
+/// tokens.
+///
+/// Lines are 1-based and columns are 0-based byte offsets, as reported by
+/// `proc_macro2`. They are only meaningful when the tree was parsed from a
+/// string (for example with `syn::parse_str` or `syn::parse_file`); inside a
+/// procedural macro the compiler does not expose them and they are zero.
+#[derive(Debug, Clone, Copy)]
+pub struct Location {
+    pub start_span: Span,
+    pub end_span: Span,
+    pub start: LineColumn,
+    pub end: LineColumn,
+}
+
+impl Location {
+    pub fn new(start_span: Span, end_span: Span) -> Location {
+        Location {
+            start_span,
+            end_span,
+            start: start_span.start(),
+            end: end_span.end(),
+        }
+    }
+    /// Byte range of the node inside `source`, the string the tree was
+    /// parsed from.
+    pub fn byte_range(&self, source: &str) -> Option<Range<usize>> {
+        let start = byte_offset(source, self.start)?;
+        let end = byte_offset(source, self.end)?;
+        if start <= end {
+            Some(start..end)
+        } else {
+            None
+        }
+    }
+}
+
+impl PartialEq for Location {
+    fn eq(&self, other: &Location) -> bool {
+        self.start == other.start && self.end == other.end
+    }
+}
+
+impl Eq for Location {}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.start.line, self.start.column + 1)
+    }
+}
+
+/// `source` without the byte order mark `syn::parse_file` skips, which the
+/// columns of the first line do not count.
+pub(crate) fn strip_bom(source: &str) -> &str {
+    source.strip_prefix('\u{feff}').unwrap_or(source)
+}
+
+fn byte_offset(source: &str, position: LineColumn) -> Option<usize> {
+    if position.line == 0 {
+        return None;
+    }
+    let mut line_start = source.len() - strip_bom(source).len();
+    for _ in 1..position.line {
+        line_start += source[line_start..].find('\n')? + 1;
+    }
+    let offset = line_start + position.column;
+    if offset <= source.len() {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+pub(crate) trait TokenRange {
+    fn token_range(&self) -> Option<(Span, Span)>;
+}
+
+pub(crate) fn tokens_range<T: ToTokens>(node: &T) -> Option<(Span, Span)> {
+    let mut tokens = node.into_token_stream().into_iter();
+    let first = tokens.next()?.span();
+    let last = tokens.last().map_or(first, |token| token.span());
+    Some((first, last))
+}
+
+fn join(start: Option<(Span, Span)>, end: Option<(Span, Span)>) -> Option<(Span, Span)> {
+    match (start, end) {
+        (Some((start, _)), Some((_, end))) => Some((start, end)),
+        (start, None) => start,
+        (None, end) => end,
+    }
+}
+
+impl TokenRange for Span {
+    fn token_range(&self) -> Option<(Span, Span)> {
+        Some((*self, *self))
+    }
+}
+
+impl TokenRange for AttrStyle {
+    fn token_range(&self) -> Option<(Span, Span)> {
+        match *self {
+            AttrStyle::Outer => None,
+            AttrStyle::Inner(ref bang) => Some((bang.0[0], bang.0[0])),
+        }
+    }
+}
+
+impl TokenRange for Data {
+    fn token_range(&self) -> Option<(Span, Span)> {
+        match *self {
+            Data::Struct(ref data) => data.token_range(),
+            Data::Enum(ref data) => data.token_range(),
+            Data::Union(ref data) => data.token_range(),
+        }
+    }
+}
+
+impl TokenRange for DataStruct {
+    fn token_range(&self) -> Option<(Span, Span)> {
+        let start = Some((self.struct_token.0, self.struct_token.0));
+        let end = match self.semi_token {
+            Some(ref semi) => Some((semi.0[0], semi.0[0])),
+            None => tokens_range(&self.fields),
+        };
+        join(start, end)
+    }
+}
+
+impl TokenRange for DataEnum {
+    fn token_range(&self) -> Option<(Span, Span)> {
+        Some((self.enum_token.0, self.brace_token.0))
+    }
+}
+
+impl TokenRange for DataUnion {
+    fn token_range(&self) -> Option<(Span, Span)> {
+        let start = Some((self.union_token.0, self.union_token.0));
+        join(start, tokens_range(&self.fields))
+    }
+}
+
+impl TokenRange for FnDecl {
+    fn token_range(&self) -> Option<(Span, Span)> {
+        let start = Some((self.fn_token.0, self.fn_token.0));
+        let end = join(
+            Some((self.paren_token.0, self.paren_token.0)),
+            tokens_range(&self.output),
+        );
+        let end = match self.generics.where_clause {
+            Some(ref where_clause) => join(end, tokens_range(where_clause)),
+            None => end,
+        };
+        join(start, end)
+    }
+}
+
+impl TokenRange for MacroDelimiter {
+    fn token_range(&self) -> Option<(Span, Span)> {
+        let span = match *self {
+            MacroDelimiter::Paren(ref paren) => paren.0,
+            MacroDelimiter::Brace(ref brace) => brace.0,
+            MacroDelimiter::Bracket(ref bracket) => bracket.0,
+        };
+        Some((span, span))
+    }
+}
+
+impl TokenRange for QSelf {
+    fn token_range(&self) -> Option<(Span, Span)> {
+        Some((self.lt_token.0[0], self.gt_token.0[0]))
+    }
+}
+
+impl TokenRange for RangeLimits {
+    fn token_range(&self) -> Option<(Span, Span)> {
+        match *self {
+            RangeLimits::HalfOpen(ref dots) => Some((dots.0[0], dots.0[1])),
+            RangeLimits::Closed(ref dots) => Some((dots.0[0], dots.0[2])),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use proc_macro2::TokenStream;
+use syn::punctuated::Punctuated;
+use syn::synom::Parser;
+use syn::token::Comma;
+use syn::{Block, Expr, File, Macro};
+use {DynNode, Node, Queryable};
+
+/// Path segment between a `Macro` and the nodes parsed from its tokens:
+/// the `i`th of them is at the macro's path followed by `MACRO_BODY, i`.
+pub const MACRO_BODY: i64 = -1;
+
+/// The tokens of `mac` parsed as an expression, a comma-separated list of
+/// expressions, items or statements, the first that succeeds.
+pub(crate) fn body(mac: &Macro) -> Vec<DynNode> {
+    let tts: &TokenStream = &mac.tts;
+    if tts.is_empty() {
+        return Vec::new();
+    }
+    if let Ok(expr) = syn::parse2::<Expr>(tts.to_owned()) {
+        return vec![expr.to_dyn()];
+    }
+    let list = Punctuated::<Expr, Comma>::parse_terminated;
+    if let Ok(exprs) = list.parse2(tts.to_owned()) {
+        return exprs.iter().map(Queryable::to_dyn).collect();
+    }
+    if let Ok(file) = syn::parse2::<File>(tts.to_owned()) {
+        return file.items.iter().map(Queryable::to_dyn).collect();
+    }
+    if let Ok(stmts) = Block::parse_within.parse2(tts.to_owned()) {
+        return stmts.iter().map(Queryable::to_dyn).collect();
+    }
+    Vec::new()
+}
+
+/// `Queryable::visit`, continued into the bodies of the macros found.
+pub(crate) fn visit<U: Queryable, T: Queryable>(node: &T, base: Vec<i64>) -> Vec<Node<U>> {
+    let mut nodes = node.visit::<U>(base.to_owned(), None);
+    for mac in node.visit::<Macro>(base, None) {
+        for (i, inner) in body(&mac.data).iter().enumerate() {
+            let mut path = mac.path.to_owned();
+            path.push(MACRO_BODY);
+            path.push(i as i64);
+            nodes.extend(visit(inner, path));
+        }
+    }
+    nodes.sort();
+    nodes
+}
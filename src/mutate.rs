@@ -0,0 +1,395 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::ops::Bound;
+use syn::punctuated::Punctuated;
+use syn::*;
+use {visit_root_mut, DynNode, Node, NodeKind, QueryResult, Queryable};
+
+/// Error returned when an edit cannot be applied, in which case the root
+/// is not returned at all. It holds the path of the first such node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MutateError {
+    /// No node of the root is at the path, as for the nodes found in macro
+    /// bodies.
+    NotFound(Vec<i64>),
+    /// The node is not an element of a `Vec` or `Punctuated` field, so it
+    /// cannot be removed or given siblings.
+    NotInContainer(Vec<i64>),
+    /// The value does not have the type of the node it replaces or of the
+    /// elements of the container it is inserted into.
+    TypeMismatch(Vec<i64>),
+}
+
+impl fmt::Display for MutateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MutateError::NotFound(ref path) => write!(f, "no node at {:?}", path),
+            MutateError::NotInContainer(ref path) => {
+                write!(f, "node at {:?} is not in a Vec or Punctuated field", path)
+            }
+            MutateError::TypeMismatch(ref path) => {
+                write!(f, "value does not fit the node at {:?}", path)
+            }
+        }
+    }
+}
+
+impl Error for MutateError {}
+
+enum Edit<V> {
+    Replace(V),
+    Remove,
+    Before(V),
+    After(V),
+}
+
+enum Action<E> {
+    Remove,
+    Before(E),
+    After(E),
+}
+
+/// Enums whose only child is the variant payload, so removing or inserting
+/// next to `Item::Fn(ItemFn)` can be requested through the `ItemFn`.
+pub(crate) const WRAPPERS: &[NodeKind] = &[
+    NodeKind::Item,
+    NodeKind::ImplItem,
+    NodeKind::TraitItem,
+    NodeKind::ForeignItem,
+    NodeKind::Stmt,
+    NodeKind::Expr,
+    NodeKind::Pat,
+    NodeKind::Type,
+    NodeKind::FnArg,
+    NodeKind::GenericParam,
+    NodeKind::WherePredicate,
+    NodeKind::GenericArgument,
+    NodeKind::UseTree,
+];
+
+pub(crate) struct Mutate<V> {
+    path: Vec<i64>,
+    edits: BTreeMap<Vec<i64>, Edit<V>>,
+    elements: HashMap<(TypeId, usize), Vec<i64>>,
+    /// Paths of the edits whose node was reached.
+    seen: BTreeSet<Vec<i64>>,
+    /// Paths of the edits applied, and of those whose value did not fit.
+    applied: RefCell<BTreeSet<Vec<i64>>>,
+    mismatched: RefCell<BTreeSet<Vec<i64>>>,
+}
+
+fn address<X: Any>(node: &X) -> (TypeId, usize) {
+    (TypeId::of::<X>(), node as *const X as usize)
+}
+
+fn convert<V: Queryable, X: Queryable>(value: &V) -> Option<X> {
+    X::downcast_from(value).or_else(|| value.to_dyn().downcast())
+}
+
+impl<V: Queryable> Mutate<V> {
+    fn new(edits: BTreeMap<Vec<i64>, Edit<V>>) -> Mutate<V> {
+        Mutate {
+            path: Vec::new(),
+            edits,
+            elements: HashMap::new(),
+            seen: BTreeSet::new(),
+            applied: RefCell::new(BTreeSet::new()),
+            mismatched: RefCell::new(BTreeSet::new()),
+        }
+    }
+    fn first_edit_from(&self, from: Bound<&[i64]>) -> Option<&Vec<i64>> {
+        self.edits
+            .range::<[i64], _>((from, Bound::Unbounded))
+            .next()
+            .map(|(key, _)| key)
+    }
+    pub(crate) fn enter<X: Queryable>(&mut self, node: &mut X) -> bool {
+        let remember = self
+            .first_edit_from(Bound::Included(&self.path))
+            .is_some_and(|key| {
+                key == &self.path
+                    || (key.len() == self.path.len() + 1
+                        && key.starts_with(&self.path)
+                        && key[self.path.len()] == 0)
+            });
+        if remember {
+            self.elements.insert(address(node), self.path.to_owned());
+        }
+        if self.edits.contains_key(&self.path) {
+            self.seen.insert(self.path.to_owned());
+        }
+        if let Some(Edit::Replace(value)) = self.edits.get(&self.path) {
+            match convert(value) {
+                Some(value) => {
+                    *node = value;
+                    self.applied.borrow_mut().insert(self.path.to_owned());
+                    return false;
+                }
+                None => {
+                    self.mismatched.borrow_mut().insert(self.path.to_owned());
+                }
+            }
+        }
+        self.path.push(0);
+        true
+    }
+    pub(crate) fn leave<X: Queryable>(&mut self, node: &mut X) {
+        self.path.pop();
+        let contains_edit = self
+            .first_edit_from(Bound::Excluded(&self.path))
+            .is_some_and(|key| key.len() > self.path.len() && key.starts_with(&self.path));
+        if contains_edit {
+            sweep(self, node);
+        }
+    }
+    pub(crate) fn next(&mut self) {
+        if let Some(last) = self.path.last_mut() {
+            *last += 1
+        }
+    }
+    fn action<E: Queryable>(&self, element: &E) -> Option<Action<E>> {
+        let path = self.elements.get(&address(element))?;
+        let (key, edit) = self.edits.get_key_value(path).or_else(|| {
+            if WRAPPERS.contains(&element.kind()) {
+                let mut payload = path.to_owned();
+                payload.push(0);
+                self.edits.get_key_value(&payload)
+            } else {
+                None
+            }
+        })?;
+        let action = match *edit {
+            Edit::Replace(_) => return None,
+            Edit::Remove => Some(Action::Remove),
+            Edit::Before(ref value) => convert(value).map(Action::Before),
+            Edit::After(ref value) => convert(value).map(Action::After),
+        };
+        match action {
+            Some(_) => self.applied.borrow_mut().insert(key.to_owned()),
+            None => self.mismatched.borrow_mut().insert(key.to_owned()),
+        };
+        action
+    }
+    /// The first edit not applied, other than those inside a replaced node.
+    fn check(&self) -> Result<(), MutateError> {
+        let applied = self.applied.borrow();
+        let replaced: Vec<&Vec<i64>> = applied
+            .iter()
+            .filter(|path| matches!(self.edits.get(*path), Some(Edit::Replace(_))))
+            .collect();
+        for path in self.edits.keys() {
+            let covered = replaced
+                .iter()
+                .any(|replaced| path.len() > replaced.len() && path.starts_with(replaced));
+            if applied.contains(path) || covered {
+                continue;
+            }
+            return Err(if self.mismatched.borrow().contains(path) {
+                MutateError::TypeMismatch(path.to_owned())
+            } else if self.seen.contains(path) {
+                MutateError::NotInContainer(path.to_owned())
+            } else {
+                MutateError::NotFound(path.to_owned())
+            });
+        }
+        Ok(())
+    }
+}
+
+trait Container {
+    fn sweep<V: Queryable>(&mut self, mutate: &Mutate<V>);
+}
+
+impl<E: Queryable> Container for Vec<E> {
+    fn sweep<V: Queryable>(&mut self, mutate: &Mutate<V>) {
+        let actions: Vec<_> = self.iter().map(|element| mutate.action(element)).collect();
+        if actions.iter().all(Option::is_none) {
+            return;
+        }
+        let old = mem::take(self);
+        for (element, action) in old.into_iter().zip(actions) {
+            match action {
+                None => self.push(element),
+                Some(Action::Remove) => {}
+                Some(Action::Before(value)) => {
+                    self.push(value);
+                    self.push(element);
+                }
+                Some(Action::After(value)) => {
+                    self.push(element);
+                    self.push(value);
+                }
+            }
+        }
+    }
+}
+
+impl<E: Queryable, P: Default> Container for Punctuated<E, P> {
+    fn sweep<V: Queryable>(&mut self, mutate: &Mutate<V>) {
+        let actions: Vec<_> = self.iter().map(|element| mutate.action(element)).collect();
+        if actions.iter().all(Option::is_none) {
+            return;
+        }
+        let trailing = self.trailing_punct();
+        let old = mem::replace(self, Punctuated::new());
+        for (element, action) in old.into_iter().zip(actions) {
+            match action {
+                None => self.push(element),
+                Some(Action::Remove) => {}
+                Some(Action::Before(value)) => {
+                    self.push(value);
+                    self.push(element);
+                }
+                Some(Action::After(value)) => {
+                    self.push(element);
+                    self.push(value);
+                }
+            }
+        }
+        if trailing && !self.empty_or_trailing() {
+            self.push_punct(P::default());
+        }
+    }
+}
+
+impl<D, C: Container> Container for (D, C) {
+    fn sweep<V: Queryable>(&mut self, mutate: &Mutate<V>) {
+        self.1.sweep(mutate)
+    }
+}
+
+impl<C: Container> Container for Option<C> {
+    fn sweep<V: Queryable>(&mut self, mutate: &Mutate<V>) {
+        if let Some(ref mut container) = *self {
+            container.sweep(mutate)
+        }
+    }
+}
+
+macro_rules! containers {
+    ($( $parent:ident { $($field:ident),* } )*) => {
+        fn sweep<V: Queryable>(mutate: &Mutate<V>, node: &mut dyn Any) {
+            $(
+                if let Some(node) = node.downcast_mut::<$parent>() {
+                    $( node.$field.sweep(mutate); )*
+                    return;
+                }
+            )*
+        }
+    };
+}
+
+containers! {
+    File { attrs, items }
+    Block { stmts }
+    ItemMod { attrs, content }
+    ItemImpl { attrs, items }
+    ItemTrait { attrs, items }
+    ItemForeignMod { attrs, items }
+    ItemEnum { attrs, variants }
+    ItemFn { attrs }
+    ItemStruct { attrs }
+    ItemUnion { attrs }
+    ItemConst { attrs }
+    ItemStatic { attrs }
+    ItemType { attrs }
+    ItemUse { attrs }
+    ItemExternCrate { attrs }
+    ItemMacro { attrs }
+    ItemMacro2 { attrs }
+    ImplItemMethod { attrs }
+    ImplItemConst { attrs }
+    ImplItemType { attrs }
+    ImplItemMacro { attrs }
+    TraitItemMethod { attrs }
+    TraitItemConst { attrs }
+    TraitItemType { attrs }
+    TraitItemMacro { attrs }
+    ForeignItemFn { attrs }
+    ForeignItemStatic { attrs }
+    ForeignItemType { attrs }
+    DeriveInput { attrs }
+    DataEnum { variants }
+    Variant { attrs }
+    Field { attrs }
+    FieldsNamed { named }
+    FieldsUnnamed { unnamed }
+    Local { attrs }
+    ExprStruct { attrs, fields }
+    ExprCall { attrs, args }
+    ExprMethodCall { attrs, args }
+    ExprArray { attrs, elems }
+    ExprTuple { attrs, elems }
+    ExprMatch { attrs, arms }
+    FnDecl { inputs }
+    Generics { params }
+    WhereClause { predicates }
+    PatStruct { fields }
+    UseGroup { items }
+    AngleBracketedGenericArguments { args }
+    TypeTuple { elems }
+}
+
+fn apply<V: Queryable, R: Queryable>(
+    root: &R,
+    edits: BTreeMap<Vec<i64>, Edit<V>>,
+) -> Result<R, MutateError> {
+    let mut root = root.to_owned();
+    let mut mutate = Mutate::new(edits);
+    visit_root_mut(&mut mutate, &mut root);
+    mutate.check().map(|_| root)
+}
+
+/// Edits are applied to a copy of the root, which is returned; the result
+/// set itself is left unchanged.
+///
+/// `remove`, `insert_before` and `insert_after` only act on nodes held in a
+/// `Vec` or `Punctuated` field such as `File::items`, `Block::stmts`,
+/// `attrs` or `FieldsNamed::named`. A node wrapped in an enum like
+/// `Item::Fn(ItemFn)` is edited through its wrapper, and inserted values
+/// must have the element type of the container. When any edit cannot be
+/// applied a `MutateError` is returned instead; edits inside a node that is
+/// replaced are dropped with it.
+impl<T: Queryable, R: Queryable> QueryResult<T, R> {
+    pub fn replace_with<F>(&self, mut f: F) -> Result<R, MutateError>
+    where
+        for<'r> F: FnMut(&'r Node<T>) -> T,
+    {
+        let edits = self
+            .iter()
+            .map(|node| (node.path.to_owned(), Edit::Replace(f(node))))
+            .collect();
+        apply(&*self.root, edits)
+    }
+    pub fn remove(&self) -> Result<R, MutateError> {
+        let edits = self
+            .iter()
+            .map(|node| (node.path.to_owned(), Edit::Remove::<DynNode>))
+            .collect();
+        apply(&*self.root, edits)
+    }
+    pub fn insert_before<V: Queryable, F>(&self, mut f: F) -> Result<R, MutateError>
+    where
+        for<'r> F: FnMut(&'r Node<T>) -> V,
+    {
+        let edits = self
+            .iter()
+            .map(|node| (node.path.to_owned(), Edit::Before(f(node))))
+            .collect();
+        apply(&*self.root, edits)
+    }
+    pub fn insert_after<V: Queryable, F>(&self, mut f: F) -> Result<R, MutateError>
+    where
+        for<'r> F: FnMut(&'r Node<T>) -> V,
+    {
+        let edits = self
+            .iter()
+            .map(|node| (node.path.to_owned(), Edit::After(f(node))))
+            .collect();
+        apply(&*self.root, edits)
+    }
+}
@@ -0,0 +1,263 @@
+use schema::{fields, FieldSpec, Walk};
+use std::any::Any;
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use {accept_root, NodeKind};
+
+/// One edge of a `NodePath`: the `field` of a `parent` node, and the
+/// position of the child among those reached through that field when the
+/// field can reach several (a `Vec`, a `Punctuated`, a tuple or a token with
+/// several spans).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Step {
+    pub parent: NodeKind,
+    pub field: &'static str,
+    pub index: Option<usize>,
+}
+
+impl Step {
+    fn field(&self) -> Option<(usize, &'static FieldSpec)> {
+        fields(self.parent)
+            .iter()
+            .enumerate()
+            .find(|&(_, field)| field.name == self.field)
+    }
+    /// Kind of the child; the tokens of a tuple field such as
+    /// `ExprIf.else_branch` are `Span`s even though this is the other kind.
+    pub fn kind(&self) -> Option<NodeKind> {
+        self.field().map(|(_, field)| field.kind)
+    }
+}
+
+impl Ord for Step {
+    fn cmp(&self, other: &Step) -> Ordering {
+        let rank = |step: &Step| step.field().map(|(rank, _)| rank);
+        rank(self)
+            .cmp(&rank(other))
+            .then(self.index.cmp(&other.index))
+            .then(self.parent.cmp(&other.parent))
+            .then(self.field.cmp(other.field))
+    }
+}
+
+impl PartialOrd for Step {
+    fn partial_cmp(&self, other: &Step) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Path of a node from the root through named fields, displayed as
+/// `ExprStruct.fields[1].member`.
+///
+/// Unlike `Node::path` it does not depend on how many tokens precede a
+/// node. Paths of the same root sort in document order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodePath {
+    pub root: NodeKind,
+    pub steps: Vec<Step>,
+}
+
+impl NodePath {
+    pub fn new(root: NodeKind) -> NodePath {
+        NodePath {
+            root,
+            steps: Vec::new(),
+        }
+    }
+    pub fn parse(path: &str) -> Result<NodePath, NodePathError> {
+        let chars: Vec<char> = path.chars().collect();
+        let mut index = 0;
+        let name = |index: &mut usize| {
+            let start = *index;
+            while *index < chars.len() && (chars[*index].is_alphanumeric() || chars[*index] == '_')
+            {
+                *index += 1;
+            }
+            chars[start..*index].iter().collect::<String>()
+        };
+        let root = name(&mut index);
+        let mut path = match NodeKind::from_name(&root) {
+            Some(kind) => NodePath::new(kind),
+            None if root.is_empty() => return Err(NodePathError::new(1, "expected a node kind")),
+            None => {
+                return Err(NodePathError::new(
+                    1,
+                    format!("unknown node kind `{}`", root),
+                ))
+            }
+        };
+        let mut parent = path.root;
+        while index < chars.len() {
+            if chars[index] != '.' {
+                return Err(NodePathError::new(
+                    index + 1,
+                    format!("unexpected character `{}`", chars[index]),
+                ));
+            }
+            index += 1;
+            let column = index + 1;
+            let name = name(&mut index);
+            if name.is_empty() {
+                return Err(NodePathError::new(column, "expected a field name"));
+            }
+            let field = match fields(parent).iter().find(|field| field.name == name) {
+                Some(field) => field,
+                None => {
+                    return Err(NodePathError::new(
+                        column,
+                        format!("`{}` has no field `{}`", parent, name),
+                    ))
+                }
+            };
+            let mut position = None;
+            if index < chars.len() && chars[index] == '[' {
+                let start = index + 1;
+                while index < chars.len() && chars[index] != ']' {
+                    index += 1;
+                }
+                if index == chars.len() {
+                    return Err(NodePathError::new(start, "expected `]`"));
+                }
+                let digits: String = chars[start..index].iter().collect();
+                position = match digits.parse() {
+                    Ok(position) => Some(position),
+                    Err(_) => return Err(NodePathError::new(start + 1, "expected an index")),
+                };
+                index += 1;
+            }
+            if field.indexed && position.is_none() {
+                return Err(NodePathError::new(
+                    index + 1,
+                    format!("expected an index after `{}`", name),
+                ));
+            }
+            if !field.indexed && field.kind != NodeKind::Span && position.is_some() {
+                return Err(NodePathError::new(
+                    column,
+                    format!("field `{}` takes no index", name),
+                ));
+            }
+            path.steps.push(Step {
+                parent,
+                field: field.name,
+                index: position,
+            });
+            parent = field.kind;
+        }
+        Ok(path)
+    }
+    /// Kind of the node the path leads to.
+    pub fn kind(&self) -> Option<NodeKind> {
+        match self.steps.last() {
+            Some(step) => step.kind(),
+            None => Some(self.root),
+        }
+    }
+}
+
+impl fmt::Display for NodePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.root)?;
+        for step in &self.steps {
+            write!(f, ".{}", step.field)?;
+            if let Some(index) = step.index {
+                write!(f, "[{}]", index)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for NodePath {
+    type Err = NodePathError;
+    fn from_str(s: &str) -> Result<NodePath, NodePathError> {
+        NodePath::parse(s)
+    }
+}
+
+/// Error returned when a node path fails to parse, `column` is 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodePathError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl NodePathError {
+    fn new<S: Into<String>>(column: usize, message: S) -> NodePathError {
+        NodePathError {
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for NodePathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl Error for NodePathError {}
+
+struct Frame {
+    kind: NodeKind,
+    edge: (&'static str, bool),
+    last: Option<&'static str>,
+    ordinal: usize,
+}
+
+/// Records the step leading to every node, in the same order as
+/// `Queryable::visit`.
+pub(crate) struct Edges {
+    frames: Vec<Frame>,
+    steps: Vec<Option<Step>>,
+}
+
+impl Edges {
+    pub(crate) fn enter(&mut self, kind: NodeKind) {
+        let step = self.frames.last_mut().map(|frame| {
+            let (field, indexed) = frame.edge;
+            if frame.last == Some(field) {
+                frame.ordinal += 1;
+            } else {
+                frame.last = Some(field);
+                frame.ordinal = 0;
+            }
+            Step {
+                parent: frame.kind,
+                field,
+                index: if indexed { Some(frame.ordinal) } else { None },
+            }
+        });
+        self.steps.push(step);
+        self.frames.push(Frame {
+            kind,
+            edge: ("", false),
+            last: None,
+            ordinal: 0,
+        });
+    }
+    pub(crate) fn leave(&mut self) {
+        self.frames.pop();
+    }
+}
+
+impl<'ast> Walk<'ast> for Edges {
+    fn edge(&mut self, field: &'static str, indexed: bool) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.edge = (field, indexed);
+        }
+    }
+}
+
+/// Steps of every node under `root` in visiting order, `None` for the root.
+pub(crate) fn steps(root: &dyn Any) -> Vec<Option<Step>> {
+    let mut edges = Edges {
+        frames: Vec::new(),
+        steps: Vec::new(),
+    };
+    accept_root(&mut edges, root);
+    edges.steps
+}
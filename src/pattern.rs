@@ -0,0 +1,363 @@
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use syn::parse_str;
+use syn::synom::Synom;
+use {fetch, Document, DynNode, Node, NodeKind, QueryResult, Queryable};
+
+const PLACEHOLDER: &str = "__synq_";
+const REST_PLACEHOLDER: &str = "__synq_rest_";
+
+/// A syntax fragment such as `$recv.unwrap()` or `Option<$T>`, matched
+/// against subtrees without regard to spans.
+///
+/// `$name` stands for any single node and `$..name` for any run of items of
+/// a list, e.g. the arguments in `foo($first, $..rest)`. A name used twice
+/// must bind equal nodes.
+#[derive(Debug, Clone)]
+pub struct Pattern<T> {
+    document: Document,
+    /// The text of each node of the template, by id.
+    texts: Vec<Option<String>>,
+    metavars: BTreeMap<usize, Metavar>,
+    marker: PhantomData<T>,
+}
+
+#[derive(Debug, Clone)]
+struct Metavar {
+    name: String,
+    rest: bool,
+}
+
+/// Error returned when a pattern fails to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    pub message: String,
+}
+
+impl PatternError {
+    fn new<S: Into<String>>(message: S) -> PatternError {
+        PatternError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for PatternError {}
+
+/// A node matched by a `Pattern` and the nodes bound to its metavariables.
+#[derive(Debug, Clone)]
+pub struct Match<T> {
+    pub node: Node<T>,
+    pub bindings: Bindings,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    nodes: BTreeMap<String, Vec<Node<DynNode>>>,
+}
+
+impl Bindings {
+    pub fn names(&self) -> Vec<&str> {
+        self.nodes.keys().map(|name| name.as_str()).collect()
+    }
+    pub fn get_dyn(&self, name: &str) -> Option<&[Node<DynNode>]> {
+        self.nodes.get(name).map(|nodes| nodes.as_slice())
+    }
+    /// The node bound to `name` as a `U`; a `GenericArgument` bound to `$T`
+    /// can be read as the `Type` spanning the same tokens.
+    pub fn get<U: Queryable>(&self, name: &str) -> Option<Node<U>> {
+        self.get_all(name).into_iter().next()
+    }
+    /// Every node bound to a `$..name` metavariable.
+    pub fn get_all<U: Queryable>(&self, name: &str) -> Vec<Node<U>> {
+        self.get_dyn(name)
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|node| {
+                let bound = text(&node.data);
+                node.data
+                    .visit::<U>(node.path.to_owned(), None)
+                    .into_iter()
+                    .find(|inner| bound == inner.data.tokens().map(|tokens| tokens.to_string()))
+            })
+            .collect()
+    }
+    fn bind(&mut self, name: &str, nodes: Vec<Node<DynNode>>) -> bool {
+        match self.nodes.get(name) {
+            Some(bound) => {
+                bound.len() == nodes.len()
+                    && bound
+                        .iter()
+                        .zip(&nodes)
+                        .all(|(a, b)| text(&a.data) == text(&b.data))
+            }
+            None => {
+                self.nodes.insert(name.to_owned(), nodes);
+                true
+            }
+        }
+    }
+}
+
+fn text(node: &DynNode) -> Option<String> {
+    node.tokens().map(|tokens| tokens.to_string())
+}
+
+/// The document a pattern is matched against. Its nodes are fetched from
+/// the root only when compared by text or bound.
+struct Subject<'a> {
+    document: &'a Document,
+    root: &'a dyn Any,
+    texts: HashMap<usize, Option<String>>,
+}
+
+impl<'a> Subject<'a> {
+    fn nodes(&self, ids: &[usize]) -> Vec<Node<DynNode>> {
+        fetch(
+            self.root,
+            ids.iter().map(|&id| self.document.path(id)).collect(),
+        )
+    }
+    fn text(&self, id: usize) -> Option<&String> {
+        self.texts.get(&id).and_then(|text| text.as_ref())
+    }
+}
+
+/// Replaces `$name` with `__synq_name` and `$..name` with
+/// `__synq_rest_name` outside of string literals.
+fn substitute(pattern: &str) -> Result<(String, Vec<Metavar>), PatternError> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut source = String::new();
+    let mut metavars = Vec::new();
+    let mut index = 0;
+    let mut quoted = false;
+    while index < chars.len() {
+        let c = chars[index];
+        index += 1;
+        if quoted || c != '$' {
+            if c == '"' {
+                quoted = !quoted;
+            } else if c == '\\' && quoted && index < chars.len() {
+                source.push(c);
+                source.push(chars[index]);
+                index += 1;
+                continue;
+            }
+            source.push(c);
+            continue;
+        }
+        let column = index;
+        let rest = chars[index..].starts_with(&['.', '.']);
+        if rest {
+            index += 2;
+        }
+        let start = index;
+        while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_') {
+            index += 1;
+        }
+        if start == index {
+            return Err(PatternError::new(format!(
+                "expected a metavariable name after `$` at column {}",
+                column
+            )));
+        }
+        let name: String = chars[start..index].iter().collect();
+        source.push_str(if rest { REST_PLACEHOLDER } else { PLACEHOLDER });
+        source.push_str(&name);
+        metavars.push(Metavar { name, rest });
+    }
+    Ok((source, metavars))
+}
+
+impl<T: Queryable + Synom> Pattern<T> {
+    pub fn parse(pattern: &str) -> Result<Pattern<T>, PatternError> {
+        let (source, names) = substitute(pattern)?;
+        let template = parse_str::<T>(&source)
+            .map_err(|error| PatternError::new(format!("cannot parse `{}`: {}", pattern, error)))?;
+        let document = Document::new(&template);
+        let texts: Vec<Option<String>> = template
+            .visit::<DynNode>(Vec::new(), None)
+            .iter()
+            .map(|node| text(&node.data))
+            .collect();
+        let mut metavars = BTreeMap::new();
+        for (id, text) in texts.iter().enumerate() {
+            let text = match *text {
+                Some(ref text) => text,
+                None => continue,
+            };
+            let metavar = names.iter().find(|metavar| {
+                let prefix = if metavar.rest {
+                    REST_PLACEHOLDER
+                } else {
+                    PLACEHOLDER
+                };
+                text.starts_with(prefix) && text[prefix.len()..] == metavar.name
+            });
+            if let Some(metavar) = metavar {
+                metavars.insert(id, metavar.clone());
+            }
+        }
+        Ok(Pattern {
+            document,
+            texts,
+            metavars,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<T: Queryable + Synom> FromStr for Pattern<T> {
+    type Err = PatternError;
+    fn from_str(s: &str) -> Result<Pattern<T>, PatternError> {
+        Pattern::parse(s)
+    }
+}
+
+impl<T: Queryable> Pattern<T> {
+    /// Matches among the nodes of `within` and their descendants, in
+    /// document order.
+    pub fn find<X: Queryable, R: Queryable>(&self, within: &QueryResult<X, R>) -> Vec<Match<T>> {
+        let document = within.document();
+        let candidates = within.query_any(&[self.document[0].kind]);
+        let ids: Vec<usize> = document.ids(candidates.iter().map(|node| &node.path));
+        let subject = Subject {
+            document,
+            root: &*within.root,
+            texts: self.texts(document, &*within.root, &ids),
+        };
+        let mut matches = Vec::new();
+        for candidate in candidates {
+            let id = match document.id(&candidate.path) {
+                Some(id) => id,
+                None => continue,
+            };
+            let mut bindings = Bindings::default();
+            if self.node(0, &subject, id, &mut bindings) {
+                if let Some(node) = candidate.downcast() {
+                    matches.push(Match { node, bindings });
+                }
+            }
+        }
+        matches
+    }
+    /// Whether the template node at `id` is compared by text: attribute
+    /// and macro bodies are not visited, so their tokens are compared.
+    fn leaf(&self, id: usize) -> bool {
+        let entry = &self.document[id];
+        entry.children.is_empty()
+            || entry.kind == NodeKind::Attribute
+            || entry.kind == NodeKind::Macro
+    }
+    /// The text of the nodes under `candidates` of the kinds compared by
+    /// text, fetched in one walk.
+    fn texts(
+        &self,
+        document: &Document,
+        root: &dyn Any,
+        candidates: &[usize],
+    ) -> HashMap<usize, Option<String>> {
+        let kinds: BTreeSet<NodeKind> = (0..self.document.len())
+            .filter(|&id| self.leaf(id) && !self.metavars.contains_key(&id))
+            .map(|id| self.document[id].kind)
+            .collect();
+        let mut paths = BTreeSet::new();
+        let mut pending = candidates.to_vec();
+        let mut seen = BTreeSet::new();
+        while let Some(id) = pending.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            if kinds.contains(&document[id].kind) {
+                paths.insert(document.path(id));
+            }
+            pending.extend(&document[id].children);
+        }
+        let nodes: Vec<Node<DynNode>> = fetch(root, paths);
+        nodes
+            .iter()
+            .filter_map(|node| document.id(&node.path).map(|id| (id, text(&node.data))))
+            .collect()
+    }
+    fn node(&self, id: usize, subject: &Subject, other: usize, bindings: &mut Bindings) -> bool {
+        if let Some(metavar) = self.metavars.get(&id) {
+            return bindings.bind(&metavar.name, subject.nodes(&[other]));
+        }
+        let document = subject.document;
+        if self.document[id].kind != document[other].kind {
+            return false;
+        }
+        if self.leaf(id) && self.texts[id].as_ref() != subject.text(other) {
+            return false;
+        }
+        let fields = grouped(&self.document, id);
+        let others = grouped(document, other);
+        if others
+            .iter()
+            .any(|&(name, _)| !fields.iter().any(|&(field, _)| field == name))
+        {
+            return false;
+        }
+        fields.iter().all(|&(name, ref children)| {
+            let others = others
+                .iter()
+                .find(|&&(field, _)| field == name)
+                .map_or(&[][..], |(_, others)| &others[..]);
+            self.list(children, subject, others, bindings)
+        })
+    }
+    fn list(
+        &self,
+        ids: &[usize],
+        subject: &Subject,
+        others: &[usize],
+        bindings: &mut Bindings,
+    ) -> bool {
+        let (&id, ids) = match ids.split_first() {
+            Some(split) => split,
+            None => return others.is_empty(),
+        };
+        match self.metavars.get(&id) {
+            Some(metavar) if metavar.rest => {
+                let nodes = subject.nodes(others);
+                (0..=others.len()).any(|len| {
+                    let mut attempt = bindings.to_owned();
+                    let matched = attempt.bind(&metavar.name, nodes[..len].to_vec())
+                        && self.list(ids, subject, &others[len..], &mut attempt);
+                    if matched {
+                        *bindings = attempt;
+                    }
+                    matched
+                })
+            }
+            _ => {
+                !others.is_empty()
+                    && self.node(id, subject, others[0], bindings)
+                    && self.list(ids, subject, &others[1..], bindings)
+            }
+        }
+    }
+}
+
+/// Children of `id` grouped by the field they are reached through.
+fn grouped(document: &Document, id: usize) -> Vec<(&'static str, Vec<usize>)> {
+    let mut fields: Vec<(&'static str, Vec<usize>)> = Vec::new();
+    for &child in &document[id].children {
+        let field = document[child].step.map_or("", |step| step.field);
+        match fields.last_mut() {
+            Some(&mut (name, ref mut children)) if name == field => children.push(child),
+            _ => fields.push((field, vec![child])),
+        }
+    }
+    fields
+}
@@ -0,0 +1,281 @@
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+use {Location, Queryable};
+
+/// How `Node::render` prints a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Render<'a> {
+    /// The node's tokens on a single line.
+    Tokens,
+    /// The node's tokens with one line per statement, item, field or match
+    /// arm, indented by four spaces.
+    Pretty,
+    /// The exact text of the node in `source`, the string the root was
+    /// parsed from; falls back to `Tokens` when the node has no location in
+    /// it.
+    Source(&'a str),
+}
+
+impl<'a> Render<'a> {
+    pub(crate) fn node<T: Queryable>(self, node: &T, location: Option<Location>) -> String {
+        let tokens = node.tokens().unwrap_or_default();
+        match self {
+            Render::Tokens => print(tokens, false),
+            Render::Pretty => print(tokens, true),
+            Render::Source(source) => match location.and_then(|l| l.byte_range(source)) {
+                Some(range) => source[range].to_owned(),
+                None => print(tokens, false),
+            },
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "box", "break", "dyn", "else", "for", "if", "impl", "in", "let", "match", "move", "mut",
+    "ref", "return", "use", "where", "while", "yield",
+];
+
+const DECLARATIONS: &[&str] = &["enum", "fn", "struct", "trait", "type", "union"];
+
+enum Token {
+    Word(String),
+    Op(String),
+    Group(Delimiter, TokenStream),
+}
+
+const OPERATORS: &[&str] = &[
+    "!=", "%=", "&&", "&=", "*=", "+=", "-=", "->", "..", "...", "..=", "/=", "::", "<<", "<<=",
+    "<=", "==", "=>", ">=", ">>", ">>=", "^=", "|=", "||",
+];
+
+/// Splits a stream into words, operators and groups. Joint punctuation is
+/// only merged into the operators Rust has, as `$a:ident` or `&'a` come out
+/// of the lexer joint too.
+fn tokens(stream: TokenStream) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut op = String::new();
+    for tree in stream {
+        if let TokenTree::Punct(ref punct) = tree {
+            let c = punct.as_char();
+            let prefix = format!("{}{}", op, c);
+            if !op.is_empty() && !OPERATORS.iter().any(|known| known.starts_with(&prefix)) {
+                tokens.push(Token::Op(op.split_off(0)));
+            }
+            op.push(c);
+            if punct.spacing() == Spacing::Alone || c == '\'' {
+                tokens.push(Token::Op(op.split_off(0)));
+            }
+            continue;
+        }
+        if !op.is_empty() {
+            tokens.push(Token::Op(op.split_off(0)));
+        }
+        tokens.push(match tree {
+            TokenTree::Group(group) => Token::Group(group.delimiter(), group.stream()),
+            TokenTree::Ident(ident) => Token::Word(ident.to_string()),
+            TokenTree::Literal(literal) => Token::Word(literal.to_string()),
+            TokenTree::Punct(_) => unreachable!(),
+        });
+    }
+    if !op.is_empty() {
+        tokens.push(Token::Op(op));
+    }
+    tokens
+}
+
+#[derive(Clone, PartialEq)]
+enum Prev {
+    Start,
+    Line,
+    Word(String),
+    /// An operator, `true` when nothing may follow it after a space.
+    Op(String, bool),
+    Open,
+    Close,
+}
+
+struct Printer {
+    out: String,
+    multiline: bool,
+    indent: usize,
+    prev: Prev,
+    after_declaration: bool,
+    angles: usize,
+    closure: bool,
+}
+
+impl Printer {
+    fn space(&mut self) {
+        self.out.push(' ');
+    }
+    fn line(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+        self.prev = Prev::Line;
+    }
+    /// Whether a space may separate the previous token from the next one.
+    fn spaced(&self) -> bool {
+        match self.prev {
+            Prev::Start | Prev::Line | Prev::Open => false,
+            Prev::Op(_, tight) => !tight,
+            Prev::Word(_) | Prev::Close => true,
+        }
+    }
+    fn follows_operand(&self) -> bool {
+        match self.prev {
+            Prev::Word(ref word) => !KEYWORDS.contains(&word.as_str()),
+            Prev::Close => true,
+            _ => false,
+        }
+    }
+    fn word(&mut self, word: String) {
+        if self.spaced() {
+            self.space();
+        }
+        self.after_declaration = match self.prev {
+            Prev::Word(ref prev) => DECLARATIONS.contains(&prev.as_str()),
+            _ => false,
+        };
+        self.out.push_str(&word);
+        self.prev = Prev::Word(word);
+    }
+    fn op(&mut self, op: String) {
+        let operand = self.follows_operand();
+        let generic = op == "<"
+            && (self.after_declaration
+                || self.prev == Prev::Op("::".to_owned(), true)
+                || match self.prev {
+                    Prev::Word(ref word) => {
+                        word == "impl" || word.starts_with(|c: char| c.is_uppercase())
+                    }
+                    _ => false,
+                });
+        let closing = (op == ">" || op == ">>") && self.angles >= op.len();
+        // The parameters of a closure are written `|a, b|`.
+        let parameters = op == "|" && !operand && !self.closure;
+        let parameters_end = op == "|" && self.closure;
+        self.closure = parameters || self.closure && !parameters_end;
+        let tight_before = match op.as_str() {
+            "," | ";" | "." | "?" | ":" => true,
+            "::" | ".." | "..=" | "!" => operand,
+            _ => generic || closing || parameters_end,
+        };
+        if self.spaced() && !tight_before {
+            self.space();
+        }
+        self.out.push_str(&op);
+        if generic {
+            self.angles += 1;
+        }
+        if closing {
+            self.angles -= op.len();
+            self.prev = Prev::Close;
+            return;
+        }
+        let unary = !operand && (op == "&" || op == "&&" || op == "*" || op == "-");
+        let tight_after = generic
+            || unary
+            || parameters
+            || matches!(
+                op.as_str(),
+                "." | "::" | ".." | "..=" | "!" | "#" | "'" | "$"
+            );
+        self.prev = Prev::Op(op, tight_after);
+    }
+    fn group(&mut self, delimiter: Delimiter, stream: TokenStream) {
+        let (open, close) = match delimiter {
+            Delimiter::Parenthesis => ("(", ")"),
+            Delimiter::Bracket => ("[", "]"),
+            Delimiter::Brace => ("{", "}"),
+            Delimiter::None => ("", ""),
+        };
+        let tight = match delimiter {
+            Delimiter::Parenthesis | Delimiter::Bracket => {
+                self.follows_operand() || self.prev == Prev::Op("!".to_owned(), true)
+            }
+            _ => false,
+        };
+        if self.spaced() && !tight {
+            self.space();
+        }
+        let angles = self.angles;
+        self.angles = 0;
+        self.out.push_str(open);
+        // Braces of a path, such as `use a::{b, c}`, hold no spaces.
+        let path = self.prev == Prev::Op("::".to_owned(), true)
+            || self.prev == Prev::Word("use".to_owned());
+        if delimiter == Delimiter::Brace && !stream.is_empty() && !path {
+            if self.multiline {
+                self.indent += 1;
+                self.line();
+                self.stream(stream, true);
+                self.indent -= 1;
+                self.line();
+            } else {
+                self.space();
+                self.prev = Prev::Open;
+                self.stream(stream, false);
+                self.space();
+            }
+        } else {
+            self.prev = Prev::Open;
+            self.stream(stream, false);
+        }
+        self.out.push_str(close);
+        self.angles = angles;
+        self.prev = Prev::Close;
+    }
+    /// Prints `stream`, breaking lines between the statements, items,
+    /// fields and arms of a brace group in multi-line mode.
+    fn stream(&mut self, stream: TokenStream, block: bool) {
+        let mut attribute = false;
+        let mut tokens = tokens(stream).into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            let mut end = false;
+            let brace = matches!(token, Token::Group(Delimiter::Brace, _));
+            match token {
+                Token::Word(word) => self.word(word),
+                Token::Op(op) => {
+                    end = op == ";" || op == "," && self.angles == 0;
+                    attribute = attribute || op == "#";
+                    self.op(op);
+                }
+                Token::Group(delimiter, stream) => {
+                    end = delimiter == Delimiter::Brace
+                        || delimiter == Delimiter::Bracket && attribute;
+                    attribute = false;
+                    self.group(delimiter, stream);
+                }
+            }
+            let follows = match tokens.peek() {
+                None => false,
+                Some(Token::Op(op)) => match op.as_str() {
+                    "," | ";" | "." | "?" => false,
+                    "=" | "=>" => !brace,
+                    _ => true,
+                },
+                Some(Token::Word(word)) => !brace || word != "else",
+                Some(&Token::Group(delimiter, _)) => !brace || delimiter != Delimiter::Parenthesis,
+            };
+            if self.multiline && block && end && follows {
+                self.line();
+            }
+        }
+    }
+}
+
+/// Prints tokens as Rust code, on one line unless `multiline` is set.
+pub(crate) fn print(tokens: TokenStream, multiline: bool) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        multiline,
+        indent: 0,
+        prev: Prev::Start,
+        after_declaration: false,
+        angles: 0,
+        closure: false,
+    };
+    printer.stream(tokens, true);
+    printer.out
+}
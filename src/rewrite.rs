@@ -0,0 +1,166 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+use Node;
+
+/// Error returned by `Rewriter::finish`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RewriteError {
+    /// A node has no location in the source, for example because it was
+    /// built rather than parsed.
+    MissingLocation(Vec<i64>),
+    /// A range does not lie within the source on character boundaries.
+    OutOfBounds(Range<usize>),
+    /// Two edits touch the same text, in the order they are written.
+    Overlap(Range<usize>, Range<usize>),
+}
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RewriteError::MissingLocation(ref path) => {
+                write!(f, "node at {:?} has no location in the source", path)
+            }
+            RewriteError::OutOfBounds(ref range) => {
+                write!(f, "range {:?} is not within the source", range)
+            }
+            RewriteError::Overlap(ref first, ref second) => {
+                write!(f, "edits at {:?} and {:?} overlap", first, second)
+            }
+        }
+    }
+}
+
+impl Error for RewriteError {}
+
+#[derive(Debug, Clone)]
+struct Edit {
+    range: Range<usize>,
+    text: String,
+}
+
+/// Text edits on the source a tree was parsed from, applied at the byte
+/// ranges of its nodes so the rest of the text, comments and formatting
+/// included, is left as written.
+///
+/// Edits are given in any order against the original source. Insertions
+/// at the same place are kept in the order they are made; edits replacing
+/// the same text are an error reported by `finish`.
+#[derive(Debug, Clone)]
+pub struct Rewriter<'a> {
+    source: &'a str,
+    edits: Vec<Edit>,
+    error: Option<RewriteError>,
+}
+
+impl<'a> Rewriter<'a> {
+    pub fn new(source: &'a str) -> Rewriter<'a> {
+        Rewriter {
+            source,
+            edits: Vec::new(),
+            error: None,
+        }
+    }
+    fn range<T>(&mut self, node: &Node<T>) -> Option<Range<usize>> {
+        let range = node
+            .location
+            .and_then(|location| location.byte_range(self.source));
+        if range.is_none() && self.error.is_none() {
+            self.error = Some(RewriteError::MissingLocation(node.path.to_owned()));
+        }
+        range
+    }
+    /// The text of `node` in the source.
+    pub fn text<T>(&self, node: &Node<T>) -> Option<&'a str> {
+        let source = self.source;
+        node.location?
+            .byte_range(source)
+            .map(|range| &source[range])
+    }
+    pub fn replace<T, S: Into<String>>(&mut self, node: &Node<T>, text: S) -> &mut Rewriter<'a> {
+        if let Some(range) = self.range(node) {
+            self.replace_range(range, text);
+        }
+        self
+    }
+    pub fn delete<T>(&mut self, node: &Node<T>) -> &mut Rewriter<'a> {
+        self.replace(node, "")
+    }
+    pub fn insert_before<T, S: Into<String>>(
+        &mut self,
+        node: &Node<T>,
+        text: S,
+    ) -> &mut Rewriter<'a> {
+        if let Some(range) = self.range(node) {
+            self.replace_range(range.start..range.start, text);
+        }
+        self
+    }
+    pub fn insert_after<T, S: Into<String>>(
+        &mut self,
+        node: &Node<T>,
+        text: S,
+    ) -> &mut Rewriter<'a> {
+        if let Some(range) = self.range(node) {
+            self.replace_range(range.end..range.end, text);
+        }
+        self
+    }
+    /// Replaces the bytes in `range` of the source, inserting when it is
+    /// empty.
+    pub fn replace_range<S: Into<String>>(
+        &mut self,
+        range: Range<usize>,
+        text: S,
+    ) -> &mut Rewriter<'a> {
+        let source = self.source;
+        let valid = range.start <= range.end
+            && range.end <= source.len()
+            && source.is_char_boundary(range.start)
+            && source.is_char_boundary(range.end);
+        if valid {
+            self.edits.push(Edit {
+                range,
+                text: text.into(),
+            });
+        } else if self.error.is_none() {
+            self.error = Some(RewriteError::OutOfBounds(range));
+        }
+        self
+    }
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+    /// The source with the edits applied, or the first error: an edit that
+    /// could not be placed, or two that overlap. An insertion overlaps a
+    /// replacement only when it falls strictly inside it.
+    pub fn finish(&self) -> Result<String, RewriteError> {
+        if let Some(ref error) = self.error {
+            return Err(error.to_owned());
+        }
+        let mut edits: Vec<&Edit> = self.edits.iter().collect();
+        edits.sort_by_key(|edit| (edit.range.start, edit.range.end));
+        let mut output = String::with_capacity(self.source.len());
+        let mut last: Option<&Edit> = None;
+        let mut copied = 0;
+        for edit in edits {
+            if let Some(last) = last {
+                if edit.range.start < last.range.end {
+                    return Err(RewriteError::Overlap(
+                        last.range.to_owned(),
+                        edit.range.to_owned(),
+                    ));
+                }
+            }
+            output.push_str(&self.source[copied..edit.range.start]);
+            output.push_str(&edit.text);
+            copied = edit.range.end;
+            last = Some(edit);
+        }
+        output.push_str(&self.source[copied..]);
+        Ok(output)
+    }
+}
@@ -0,0 +1,1395 @@
+//! Field layout of every syntax type, in the order `syn::visit` reaches the
+//! children. It mirrors syn 0.14's `gen/visit.rs`: `[T]` is a `Vec` or
+//! `Punctuated`, `Token` is a token whose spans are visited, and fields that
+//! `syn::visit` skips are left out.
+
+use proc_macro2::Span;
+use syn::visit::Visit;
+use syn::*;
+use NodeKind;
+
+/// A named child edge of a syntax type.
+///
+/// `indexed` fields can reach several children, which are told apart by
+/// their position among the children reached through the field; `kind` is
+/// the kind of those children, ignoring tokens when the field is a tuple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FieldSpec {
+    pub name: &'static str,
+    pub indexed: bool,
+    pub kind: NodeKind,
+}
+
+/// A visitor told which field each following child is reached through.
+pub(crate) trait Walk<'ast>: Visit<'ast> {
+    fn edge(&mut self, field: &'static str, indexed: bool);
+}
+
+/// Walks the children of a node like `syn::visit` does, announcing the field
+/// before each of them.
+pub(crate) trait Schema {
+    fn walk<'ast, V: Walk<'ast>>(&'ast self, visitor: &mut V);
+}
+
+/// Hands a node to the matching `Visit` method.
+pub(crate) trait Accept {
+    fn accept<'ast, V: Visit<'ast>>(&'ast self, visitor: &mut V);
+}
+
+impl<T: Accept> Accept for Box<T> {
+    fn accept<'ast, V: Visit<'ast>>(&'ast self, visitor: &mut V) {
+        (**self).accept(visitor)
+    }
+}
+
+trait Spans {
+    fn spans(&self) -> &[Span];
+}
+
+impl Spans for Span {
+    fn spans(&self) -> &[Span] {
+        ::std::slice::from_ref(self)
+    }
+}
+
+macro_rules! spans_array {
+    ($($n:expr),*) => {
+        $(
+            impl Spans for [Span; $n] {
+                fn spans(&self) -> &[Span] {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+spans_array!(1, 2, 3);
+
+fn tokens<'ast, V: Walk<'ast>, S: Spans>(
+    visitor: &mut V,
+    field: &'static str,
+    indexed: bool,
+    spans: &'ast S,
+) {
+    let spans = spans.spans();
+    visitor.edge(field, indexed || spans.len() > 1);
+    for span in spans {
+        visitor.visit_span(span);
+    }
+}
+
+macro_rules! walk_field {
+    ($v:ident, $i:ident, $f:ident, Option<( $($elem:tt)* )>) => {
+        if let Some(ref it) = $i.$f {
+            walk_tuple!($v, $f, it, [0 1 2] $($elem)*);
+        }
+    };
+    ($v:ident, $i:ident, $f:ident, Option<Token>) => {
+        if let Some(ref it) = $i.$f {
+            tokens($v, stringify!($f), false, &it.0);
+        }
+    };
+    ($v:ident, $i:ident, $f:ident, Option<$t:ident>) => {
+        if let Some(ref it) = $i.$f {
+            $v.edge(stringify!($f), false);
+            it.accept($v);
+        }
+    };
+    ($v:ident, $i:ident, $f:ident, [$t:ident]) => {
+        for it in &$i.$f {
+            $v.edge(stringify!($f), true);
+            it.accept($v);
+        }
+    };
+    ($v:ident, $i:ident, $f:ident, Token) => {
+        tokens($v, stringify!($f), false, &$i.$f.0);
+    };
+    ($v:ident, $i:ident, $f:ident, $t:ident) => {
+        $v.edge(stringify!($f), false);
+        $i.$f.accept($v);
+    };
+}
+
+macro_rules! walk_tuple {
+    ($v:ident, $f:ident, $it:ident, [$($n:tt)*]) => {};
+    ($v:ident, $f:ident, $it:ident, [$($n:tt)*] , $($tail:tt)*) => {
+        walk_tuple!($v, $f, $it, [$($n)*] $($tail)*);
+    };
+    ($v:ident, $f:ident, $it:ident, [$n:tt $($rest:tt)*] Option<Token> $($tail:tt)*) => {
+        if let Some(ref token) = $it.$n {
+            tokens($v, stringify!($f), true, &token.0);
+        }
+        walk_tuple!($v, $f, $it, [$($rest)*] $($tail)*);
+    };
+    ($v:ident, $f:ident, $it:ident, [$n:tt $($rest:tt)*] Token $($tail:tt)*) => {
+        tokens($v, stringify!($f), true, &($it.$n).0);
+        walk_tuple!($v, $f, $it, [$($rest)*] $($tail)*);
+    };
+    ($v:ident, $f:ident, $it:ident, [$n:tt $($rest:tt)*] [$t:ident] $($tail:tt)*) => {
+        for element in &$it.$n {
+            $v.edge(stringify!($f), true);
+            element.accept($v);
+        }
+        walk_tuple!($v, $f, $it, [$($rest)*] $($tail)*);
+    };
+    ($v:ident, $f:ident, $it:ident, [$n:tt $($rest:tt)*] $t:ident $($tail:tt)*) => {
+        $v.edge(stringify!($f), true);
+        $it.$n.accept($v);
+        walk_tuple!($v, $f, $it, [$($rest)*] $($tail)*);
+    };
+}
+
+macro_rules! walk_variant {
+    ($v:ident, $i:ident, $ty:ident, $variant:ident) => {};
+    ($v:ident, $i:ident, $ty:ident, $variant:ident (Token)) => {
+        if let $ty::$variant(ref it) = *$i {
+            tokens($v, stringify!($variant), false, &it.0);
+        }
+    };
+    ($v:ident, $i:ident, $ty:ident, $variant:ident ($t:ident)) => {
+        if let $ty::$variant(ref it) = *$i {
+            $v.edge(stringify!($variant), false);
+            it.accept($v);
+        }
+    };
+    ($v:ident, $i:ident, $ty:ident, $variant:ident ($($elem:tt),*)) => {
+        if let $ty::$variant(ref first, ref second) = *$i {
+            let it = (first, second);
+            walk_tuple!($v, $variant, it, [0 1] $($elem),*);
+        }
+    };
+}
+
+macro_rules! field_kind {
+    (Token) => {
+        NodeKind::Span
+    };
+    ($t:ident) => {
+        NodeKind::$t
+    };
+}
+
+macro_rules! tuple_kind {
+    () => {
+        NodeKind::Span
+    };
+    (, $($tail:tt)*) => {
+        tuple_kind!($($tail)*)
+    };
+    (Option<Token> $($tail:tt)*) => {
+        tuple_kind!($($tail)*)
+    };
+    (Token $($tail:tt)*) => {
+        tuple_kind!($($tail)*)
+    };
+    ([$t:ident] $($tail:tt)*) => {
+        NodeKind::$t
+    };
+    ($t:ident $($tail:tt)*) => {
+        NodeKind::$t
+    };
+}
+
+macro_rules! field {
+    ($f:ident, Option<( $($elem:tt)* )>) => {
+        FieldSpec { name: stringify!($f), indexed: true, kind: tuple_kind!($($elem)*) }
+    };
+    ($f:ident, Option<$t:ident>) => {
+        FieldSpec { name: stringify!($f), indexed: false, kind: field_kind!($t) }
+    };
+    ($f:ident, [$t:ident]) => {
+        FieldSpec { name: stringify!($f), indexed: true, kind: field_kind!($t) }
+    };
+    ($f:ident, $t:ident) => {
+        FieldSpec { name: stringify!($f), indexed: false, kind: field_kind!($t) }
+    };
+}
+
+macro_rules! variant {
+    ($variant:ident ($t:tt)) => {
+        FieldSpec { name: stringify!($variant), indexed: false, kind: field_kind!($t) }
+    };
+    ($variant:ident ($($elem:tt),*)) => {
+        FieldSpec { name: stringify!($variant), indexed: true, kind: tuple_kind!($($elem),*) }
+    };
+}
+
+macro_rules! schema {
+    ($( struct $ty:ident { $( $f:ident : $head:tt $(< $inner:tt >)* ),* $(,)* } )*
+     $( enum $en:ident { $( $variant:ident $( ( $($elem:tt),* ) )* ),* $(,)* } )*) => {
+        $(
+            impl Schema for $ty {
+                #[allow(unused_variables)]
+                fn walk<'ast, V: Walk<'ast>>(&'ast self, visitor: &mut V) {
+                    $( walk_field!(visitor, self, $f, $head $(< $inner >)*); )*
+                }
+            }
+        )*
+        $(
+            impl Schema for $en {
+                fn walk<'ast, V: Walk<'ast>>(&'ast self, visitor: &mut V) {
+                    $( walk_variant!(visitor, self, $en, $variant $( ( $($elem),* ) )*); )*
+                }
+            }
+        )*
+        /// Fields of `kind` in visiting order; variants of an enum are its
+        /// fields.
+        pub(crate) fn fields(kind: NodeKind) -> &'static [FieldSpec] {
+            match kind {
+                $( NodeKind::$ty => &[ $( field!($f, $head $(< $inner >)*) ),* ], )*
+                $( NodeKind::$en => &[ $( $( variant!($variant ( $($elem),* )) , )* )* ], )*
+                NodeKind::DocComment => &[],
+            }
+        }
+    };
+}
+
+schema! {
+    struct Abi {
+        extern_token: Token,
+        name: Option<LitStr>,
+    }
+    struct AngleBracketedGenericArguments {
+        colon2_token: Option<Token>,
+        lt_token: Token,
+        args: [GenericArgument],
+        gt_token: Token,
+    }
+    struct ArgCaptured {
+        pat: Pat,
+        colon_token: Token,
+        ty: Type,
+    }
+    struct ArgSelf {
+        mutability: Option<Token>,
+        self_token: Token,
+    }
+    struct ArgSelfRef {
+        and_token: Token,
+        lifetime: Option<Lifetime>,
+        mutability: Option<Token>,
+        self_token: Token,
+    }
+    struct Arm {
+        attrs: [Attribute],
+        leading_vert: Option<Token>,
+        pats: [Pat],
+        guard: Option<(Token, Expr)>,
+        fat_arrow_token: Token,
+        body: Expr,
+        comma: Option<Token>,
+    }
+    struct Attribute {
+        pound_token: Token,
+        style: AttrStyle,
+        bracket_token: Token,
+        path: Path,
+    }
+    struct BareFnArg {
+        name: Option<(BareFnArgName, Token)>,
+        ty: Type,
+    }
+    struct Binding {
+        ident: Ident,
+        eq_token: Token,
+        ty: Type,
+    }
+    struct Block {
+        brace_token: Token,
+        stmts: [Stmt],
+    }
+    struct BoundLifetimes {
+        for_token: Token,
+        lt_token: Token,
+        lifetimes: [LifetimeDef],
+        gt_token: Token,
+    }
+    struct ConstParam {
+        attrs: [Attribute],
+        const_token: Token,
+        ident: Ident,
+        colon_token: Token,
+        ty: Type,
+        eq_token: Option<Token>,
+        default: Option<Expr>,
+    }
+    struct DataEnum {
+        enum_token: Token,
+        brace_token: Token,
+        variants: [Variant],
+    }
+    struct DataStruct {
+        struct_token: Token,
+        fields: Fields,
+        semi_token: Option<Token>,
+    }
+    struct DataUnion {
+        union_token: Token,
+        fields: FieldsNamed,
+    }
+    struct DeriveInput {
+        attrs: [Attribute],
+        vis: Visibility,
+        ident: Ident,
+        generics: Generics,
+        data: Data,
+    }
+    struct ExprArray {
+        attrs: [Attribute],
+        bracket_token: Token,
+        elems: [Expr],
+    }
+    struct ExprAssign {
+        attrs: [Attribute],
+        left: Expr,
+        eq_token: Token,
+        right: Expr,
+    }
+    struct ExprAssignOp {
+        attrs: [Attribute],
+        left: Expr,
+        op: BinOp,
+        right: Expr,
+    }
+    struct ExprBinary {
+        attrs: [Attribute],
+        left: Expr,
+        op: BinOp,
+        right: Expr,
+    }
+    struct ExprBlock {
+        attrs: [Attribute],
+        block: Block,
+    }
+    struct ExprBox {
+        attrs: [Attribute],
+        box_token: Token,
+        expr: Expr,
+    }
+    struct ExprBreak {
+        attrs: [Attribute],
+        break_token: Token,
+        label: Option<Lifetime>,
+        expr: Option<Expr>,
+    }
+    struct ExprCall {
+        attrs: [Attribute],
+        func: Expr,
+        paren_token: Token,
+        args: [Expr],
+    }
+    struct ExprCast {
+        attrs: [Attribute],
+        expr: Expr,
+        as_token: Token,
+        ty: Type,
+    }
+    struct ExprCatch {
+        attrs: [Attribute],
+        do_token: Token,
+        catch_token: Token,
+        block: Block,
+    }
+    struct ExprClosure {
+        attrs: [Attribute],
+        movability: Option<Token>,
+        capture: Option<Token>,
+        or1_token: Token,
+        inputs: [FnArg],
+        or2_token: Token,
+        output: ReturnType,
+        body: Expr,
+    }
+    struct ExprContinue {
+        attrs: [Attribute],
+        continue_token: Token,
+        label: Option<Lifetime>,
+    }
+    struct ExprField {
+        attrs: [Attribute],
+        base: Expr,
+        dot_token: Token,
+        member: Member,
+    }
+    struct ExprForLoop {
+        attrs: [Attribute],
+        label: Option<Label>,
+        for_token: Token,
+        pat: Pat,
+        in_token: Token,
+        expr: Expr,
+        body: Block,
+    }
+    struct ExprGroup {
+        attrs: [Attribute],
+        group_token: Token,
+        expr: Expr,
+    }
+    struct ExprIf {
+        attrs: [Attribute],
+        if_token: Token,
+        cond: Expr,
+        then_branch: Block,
+        else_branch: Option<(Token, Expr)>,
+    }
+    struct ExprIfLet {
+        attrs: [Attribute],
+        if_token: Token,
+        let_token: Token,
+        pats: [Pat],
+        eq_token: Token,
+        expr: Expr,
+        then_branch: Block,
+        else_branch: Option<(Token, Expr)>,
+    }
+    struct ExprInPlace {
+        attrs: [Attribute],
+        place: Expr,
+        arrow_token: Token,
+        value: Expr,
+    }
+    struct ExprIndex {
+        attrs: [Attribute],
+        expr: Expr,
+        bracket_token: Token,
+        index: Expr,
+    }
+    struct ExprLit {
+        attrs: [Attribute],
+        lit: Lit,
+    }
+    struct ExprLoop {
+        attrs: [Attribute],
+        label: Option<Label>,
+        loop_token: Token,
+        body: Block,
+    }
+    struct ExprMacro {
+        attrs: [Attribute],
+        mac: Macro,
+    }
+    struct ExprMatch {
+        attrs: [Attribute],
+        match_token: Token,
+        expr: Expr,
+        brace_token: Token,
+        arms: [Arm],
+    }
+    struct ExprMethodCall {
+        attrs: [Attribute],
+        receiver: Expr,
+        dot_token: Token,
+        method: Ident,
+        turbofish: Option<MethodTurbofish>,
+        paren_token: Token,
+        args: [Expr],
+    }
+    struct ExprParen {
+        attrs: [Attribute],
+        paren_token: Token,
+        expr: Expr,
+    }
+    struct ExprPath {
+        attrs: [Attribute],
+        qself: Option<QSelf>,
+        path: Path,
+    }
+    struct ExprRange {
+        attrs: [Attribute],
+        from: Option<Expr>,
+        limits: RangeLimits,
+        to: Option<Expr>,
+    }
+    struct ExprReference {
+        attrs: [Attribute],
+        and_token: Token,
+        mutability: Option<Token>,
+        expr: Expr,
+    }
+    struct ExprRepeat {
+        attrs: [Attribute],
+        bracket_token: Token,
+        expr: Expr,
+        semi_token: Token,
+        len: Expr,
+    }
+    struct ExprReturn {
+        attrs: [Attribute],
+        return_token: Token,
+        expr: Option<Expr>,
+    }
+    struct ExprStruct {
+        attrs: [Attribute],
+        path: Path,
+        brace_token: Token,
+        fields: [FieldValue],
+        dot2_token: Option<Token>,
+        rest: Option<Expr>,
+    }
+    struct ExprTry {
+        attrs: [Attribute],
+        expr: Expr,
+        question_token: Token,
+    }
+    struct ExprTuple {
+        attrs: [Attribute],
+        paren_token: Token,
+        elems: [Expr],
+    }
+    struct ExprType {
+        attrs: [Attribute],
+        expr: Expr,
+        colon_token: Token,
+        ty: Type,
+    }
+    struct ExprUnary {
+        attrs: [Attribute],
+        op: UnOp,
+        expr: Expr,
+    }
+    struct ExprUnsafe {
+        attrs: [Attribute],
+        unsafe_token: Token,
+        block: Block,
+    }
+    struct ExprVerbatim {}
+    struct ExprWhile {
+        attrs: [Attribute],
+        label: Option<Label>,
+        while_token: Token,
+        cond: Expr,
+        body: Block,
+    }
+    struct ExprWhileLet {
+        attrs: [Attribute],
+        label: Option<Label>,
+        while_token: Token,
+        let_token: Token,
+        pats: [Pat],
+        eq_token: Token,
+        expr: Expr,
+        body: Block,
+    }
+    struct ExprYield {
+        attrs: [Attribute],
+        yield_token: Token,
+        expr: Option<Expr>,
+    }
+    struct Field {
+        attrs: [Attribute],
+        vis: Visibility,
+        ident: Option<Ident>,
+        colon_token: Option<Token>,
+        ty: Type,
+    }
+    struct FieldPat {
+        attrs: [Attribute],
+        member: Member,
+        colon_token: Option<Token>,
+        pat: Pat,
+    }
+    struct FieldValue {
+        attrs: [Attribute],
+        member: Member,
+        colon_token: Option<Token>,
+        expr: Expr,
+    }
+    struct FieldsNamed {
+        brace_token: Token,
+        named: [Field],
+    }
+    struct FieldsUnnamed {
+        paren_token: Token,
+        unnamed: [Field],
+    }
+    struct File {
+        attrs: [Attribute],
+        items: [Item],
+    }
+    struct FnDecl {
+        fn_token: Token,
+        generics: Generics,
+        paren_token: Token,
+        inputs: [FnArg],
+        variadic: Option<Token>,
+        output: ReturnType,
+    }
+    struct ForeignItemFn {
+        attrs: [Attribute],
+        vis: Visibility,
+        ident: Ident,
+        decl: FnDecl,
+        semi_token: Token,
+    }
+    struct ForeignItemStatic {
+        attrs: [Attribute],
+        vis: Visibility,
+        static_token: Token,
+        mutability: Option<Token>,
+        ident: Ident,
+        colon_token: Token,
+        ty: Type,
+        semi_token: Token,
+    }
+    struct ForeignItemType {
+        attrs: [Attribute],
+        vis: Visibility,
+        type_token: Token,
+        ident: Ident,
+        semi_token: Token,
+    }
+    struct ForeignItemVerbatim {}
+    struct Generics {
+        lt_token: Option<Token>,
+        params: [GenericParam],
+        gt_token: Option<Token>,
+        where_clause: Option<WhereClause>,
+    }
+    struct Ident {}
+    struct ImplItemConst {
+        attrs: [Attribute],
+        vis: Visibility,
+        defaultness: Option<Token>,
+        const_token: Token,
+        ident: Ident,
+        colon_token: Token,
+        ty: Type,
+        eq_token: Token,
+        expr: Expr,
+        semi_token: Token,
+    }
+    struct ImplItemMacro {
+        attrs: [Attribute],
+        mac: Macro,
+        semi_token: Option<Token>,
+    }
+    struct ImplItemMethod {
+        attrs: [Attribute],
+        vis: Visibility,
+        defaultness: Option<Token>,
+        sig: MethodSig,
+        block: Block,
+    }
+    struct ImplItemType {
+        attrs: [Attribute],
+        vis: Visibility,
+        defaultness: Option<Token>,
+        type_token: Token,
+        ident: Ident,
+        generics: Generics,
+        eq_token: Token,
+        ty: Type,
+        semi_token: Token,
+    }
+    struct ImplItemVerbatim {}
+    struct Index {
+        span: Span,
+    }
+    struct ItemConst {
+        attrs: [Attribute],
+        vis: Visibility,
+        const_token: Token,
+        ident: Ident,
+        colon_token: Token,
+        ty: Type,
+        eq_token: Token,
+        expr: Expr,
+        semi_token: Token,
+    }
+    struct ItemEnum {
+        attrs: [Attribute],
+        vis: Visibility,
+        enum_token: Token,
+        ident: Ident,
+        generics: Generics,
+        brace_token: Token,
+        variants: [Variant],
+    }
+    struct ItemExternCrate {
+        attrs: [Attribute],
+        vis: Visibility,
+        extern_token: Token,
+        crate_token: Token,
+        ident: Ident,
+        rename: Option<(Token, Ident)>,
+        semi_token: Token,
+    }
+    struct ItemFn {
+        attrs: [Attribute],
+        vis: Visibility,
+        constness: Option<Token>,
+        unsafety: Option<Token>,
+        abi: Option<Abi>,
+        ident: Ident,
+        decl: FnDecl,
+        block: Block,
+    }
+    struct ItemForeignMod {
+        attrs: [Attribute],
+        abi: Abi,
+        brace_token: Token,
+        items: [ForeignItem],
+    }
+    struct ItemImpl {
+        attrs: [Attribute],
+        defaultness: Option<Token>,
+        unsafety: Option<Token>,
+        impl_token: Token,
+        generics: Generics,
+        trait_: Option<(Option<Token>, Path, Token)>,
+        self_ty: Type,
+        brace_token: Token,
+        items: [ImplItem],
+    }
+    struct ItemMacro {
+        attrs: [Attribute],
+        ident: Option<Ident>,
+        mac: Macro,
+        semi_token: Option<Token>,
+    }
+    struct ItemMacro2 {
+        attrs: [Attribute],
+        vis: Visibility,
+        macro_token: Token,
+        ident: Ident,
+        paren_token: Token,
+        brace_token: Token,
+    }
+    struct ItemMod {
+        attrs: [Attribute],
+        vis: Visibility,
+        mod_token: Token,
+        ident: Ident,
+        content: Option<(Token, [Item])>,
+        semi: Option<Token>,
+    }
+    struct ItemStatic {
+        attrs: [Attribute],
+        vis: Visibility,
+        static_token: Token,
+        mutability: Option<Token>,
+        ident: Ident,
+        colon_token: Token,
+        ty: Type,
+        eq_token: Token,
+        expr: Expr,
+        semi_token: Token,
+    }
+    struct ItemStruct {
+        attrs: [Attribute],
+        vis: Visibility,
+        struct_token: Token,
+        ident: Ident,
+        generics: Generics,
+        fields: Fields,
+        semi_token: Option<Token>,
+    }
+    struct ItemTrait {
+        attrs: [Attribute],
+        vis: Visibility,
+        unsafety: Option<Token>,
+        auto_token: Option<Token>,
+        trait_token: Token,
+        ident: Ident,
+        generics: Generics,
+        colon_token: Option<Token>,
+        supertraits: [TypeParamBound],
+        brace_token: Token,
+        items: [TraitItem],
+    }
+    struct ItemType {
+        attrs: [Attribute],
+        vis: Visibility,
+        type_token: Token,
+        ident: Ident,
+        generics: Generics,
+        eq_token: Token,
+        ty: Type,
+        semi_token: Token,
+    }
+    struct ItemUnion {
+        attrs: [Attribute],
+        vis: Visibility,
+        union_token: Token,
+        ident: Ident,
+        generics: Generics,
+        fields: FieldsNamed,
+    }
+    struct ItemUse {
+        attrs: [Attribute],
+        vis: Visibility,
+        use_token: Token,
+        leading_colon: Option<Token>,
+        tree: UseTree,
+        semi_token: Token,
+    }
+    struct ItemVerbatim {}
+    struct Label {
+        name: Lifetime,
+        colon_token: Token,
+    }
+    struct Lifetime {
+        ident: Ident,
+    }
+    struct LifetimeDef {
+        attrs: [Attribute],
+        lifetime: Lifetime,
+        colon_token: Option<Token>,
+        bounds: [Lifetime],
+    }
+    struct LitBool {
+        span: Span,
+    }
+    struct LitByte {}
+    struct LitByteStr {}
+    struct LitChar {}
+    struct LitFloat {}
+    struct LitInt {}
+    struct LitStr {}
+    struct LitVerbatim {}
+    struct Local {
+        attrs: [Attribute],
+        let_token: Token,
+        pats: [Pat],
+        ty: Option<(Token, Type)>,
+        init: Option<(Token, Expr)>,
+        semi_token: Token,
+    }
+    struct Macro {
+        path: Path,
+        bang_token: Token,
+        delimiter: MacroDelimiter,
+    }
+    struct MetaList {
+        ident: Ident,
+        paren_token: Token,
+        nested: [NestedMeta],
+    }
+    struct MetaNameValue {
+        ident: Ident,
+        eq_token: Token,
+        lit: Lit,
+    }
+    struct MethodSig {
+        constness: Option<Token>,
+        unsafety: Option<Token>,
+        abi: Option<Abi>,
+        ident: Ident,
+        decl: FnDecl,
+    }
+    struct MethodTurbofish {
+        colon2_token: Token,
+        lt_token: Token,
+        args: [GenericMethodArgument],
+        gt_token: Token,
+    }
+    struct ParenthesizedGenericArguments {
+        paren_token: Token,
+        inputs: [Type],
+        output: ReturnType,
+    }
+    struct PatBox {
+        box_token: Token,
+        pat: Pat,
+    }
+    struct PatIdent {
+        by_ref: Option<Token>,
+        mutability: Option<Token>,
+        ident: Ident,
+        subpat: Option<(Token, Pat)>,
+    }
+    struct PatLit {
+        expr: Expr,
+    }
+    struct PatMacro {
+        mac: Macro,
+    }
+    struct PatPath {
+        qself: Option<QSelf>,
+        path: Path,
+    }
+    struct PatRange {
+        lo: Expr,
+        limits: RangeLimits,
+        hi: Expr,
+    }
+    struct PatRef {
+        and_token: Token,
+        mutability: Option<Token>,
+        pat: Pat,
+    }
+    struct PatSlice {
+        bracket_token: Token,
+        front: [Pat],
+        middle: Option<Pat>,
+        dot2_token: Option<Token>,
+        comma_token: Option<Token>,
+        back: [Pat],
+    }
+    struct PatStruct {
+        path: Path,
+        brace_token: Token,
+        fields: [FieldPat],
+        dot2_token: Option<Token>,
+    }
+    struct PatTuple {
+        paren_token: Token,
+        front: [Pat],
+        dot2_token: Option<Token>,
+        comma_token: Option<Token>,
+        back: [Pat],
+    }
+    struct PatTupleStruct {
+        path: Path,
+        pat: PatTuple,
+    }
+    struct PatVerbatim {}
+    struct PatWild {
+        underscore_token: Token,
+    }
+    struct Path {
+        leading_colon: Option<Token>,
+        segments: [PathSegment],
+    }
+    struct PathSegment {
+        ident: Ident,
+        arguments: PathArguments,
+    }
+    struct PredicateEq {
+        lhs_ty: Type,
+        eq_token: Token,
+        rhs_ty: Type,
+    }
+    struct PredicateLifetime {
+        lifetime: Lifetime,
+        colon_token: Option<Token>,
+        bounds: [Lifetime],
+    }
+    struct PredicateType {
+        lifetimes: Option<BoundLifetimes>,
+        bounded_ty: Type,
+        colon_token: Token,
+        bounds: [TypeParamBound],
+    }
+    struct QSelf {
+        lt_token: Token,
+        ty: Type,
+        as_token: Option<Token>,
+        gt_token: Token,
+    }
+    struct Span {}
+    struct TraitBound {
+        paren_token: Option<Token>,
+        modifier: TraitBoundModifier,
+        lifetimes: Option<BoundLifetimes>,
+        path: Path,
+    }
+    struct TraitItemConst {
+        attrs: [Attribute],
+        const_token: Token,
+        ident: Ident,
+        colon_token: Token,
+        ty: Type,
+        default: Option<(Token, Expr)>,
+        semi_token: Token,
+    }
+    struct TraitItemMacro {
+        attrs: [Attribute],
+        mac: Macro,
+        semi_token: Option<Token>,
+    }
+    struct TraitItemMethod {
+        attrs: [Attribute],
+        sig: MethodSig,
+        default: Option<Block>,
+        semi_token: Option<Token>,
+    }
+    struct TraitItemType {
+        attrs: [Attribute],
+        type_token: Token,
+        ident: Ident,
+        generics: Generics,
+        colon_token: Option<Token>,
+        bounds: [TypeParamBound],
+        default: Option<(Token, Type)>,
+        semi_token: Token,
+    }
+    struct TraitItemVerbatim {}
+    struct TypeArray {
+        bracket_token: Token,
+        elem: Type,
+        semi_token: Token,
+        len: Expr,
+    }
+    struct TypeBareFn {
+        unsafety: Option<Token>,
+        abi: Option<Abi>,
+        fn_token: Token,
+        lifetimes: Option<BoundLifetimes>,
+        paren_token: Token,
+        inputs: [BareFnArg],
+        variadic: Option<Token>,
+        output: ReturnType,
+    }
+    struct TypeGroup {
+        group_token: Token,
+        elem: Type,
+    }
+    struct TypeImplTrait {
+        impl_token: Token,
+        bounds: [TypeParamBound],
+    }
+    struct TypeInfer {
+        underscore_token: Token,
+    }
+    struct TypeMacro {
+        mac: Macro,
+    }
+    struct TypeNever {
+        bang_token: Token,
+    }
+    struct TypeParam {
+        attrs: [Attribute],
+        ident: Ident,
+        colon_token: Option<Token>,
+        bounds: [TypeParamBound],
+        eq_token: Option<Token>,
+        default: Option<Type>,
+    }
+    struct TypeParen {
+        paren_token: Token,
+        elem: Type,
+    }
+    struct TypePath {
+        qself: Option<QSelf>,
+        path: Path,
+    }
+    struct TypePtr {
+        star_token: Token,
+        const_token: Option<Token>,
+        mutability: Option<Token>,
+        elem: Type,
+    }
+    struct TypeReference {
+        and_token: Token,
+        lifetime: Option<Lifetime>,
+        mutability: Option<Token>,
+        elem: Type,
+    }
+    struct TypeSlice {
+        bracket_token: Token,
+        elem: Type,
+    }
+    struct TypeTraitObject {
+        dyn_token: Option<Token>,
+        bounds: [TypeParamBound],
+    }
+    struct TypeTuple {
+        paren_token: Token,
+        elems: [Type],
+    }
+    struct TypeVerbatim {}
+    struct UseGlob {
+        star_token: Token,
+    }
+    struct UseGroup {
+        brace_token: Token,
+        items: [UseTree],
+    }
+    struct UseName {
+        ident: Ident,
+    }
+    struct UsePath {
+        ident: Ident,
+        colon2_token: Token,
+        tree: UseTree,
+    }
+    struct UseRename {
+        ident: Ident,
+        as_token: Token,
+        rename: Ident,
+    }
+    struct Variant {
+        attrs: [Attribute],
+        ident: Ident,
+        fields: Fields,
+        discriminant: Option<(Token, Expr)>,
+    }
+    struct VisCrate {
+        crate_token: Token,
+    }
+    struct VisPublic {
+        pub_token: Token,
+    }
+    struct VisRestricted {
+        pub_token: Token,
+        paren_token: Token,
+        in_token: Option<Token>,
+        path: Path,
+    }
+    struct WhereClause {
+        where_token: Token,
+        predicates: [WherePredicate],
+    }
+    enum AttrStyle {
+        Outer,
+        Inner(Token),
+    }
+    enum BareFnArgName {
+        Named(Ident),
+        Wild(Token),
+    }
+    enum BinOp {
+        Add(Token),
+        Sub(Token),
+        Mul(Token),
+        Div(Token),
+        Rem(Token),
+        And(Token),
+        Or(Token),
+        BitXor(Token),
+        BitAnd(Token),
+        BitOr(Token),
+        Shl(Token),
+        Shr(Token),
+        Eq(Token),
+        Lt(Token),
+        Le(Token),
+        Ne(Token),
+        Ge(Token),
+        Gt(Token),
+        AddEq(Token),
+        SubEq(Token),
+        MulEq(Token),
+        DivEq(Token),
+        RemEq(Token),
+        BitXorEq(Token),
+        BitAndEq(Token),
+        BitOrEq(Token),
+        ShlEq(Token),
+        ShrEq(Token),
+    }
+    enum Data {
+        Struct(DataStruct),
+        Enum(DataEnum),
+        Union(DataUnion),
+    }
+    enum Expr {
+        Box(ExprBox),
+        InPlace(ExprInPlace),
+        Array(ExprArray),
+        Call(ExprCall),
+        MethodCall(ExprMethodCall),
+        Tuple(ExprTuple),
+        Binary(ExprBinary),
+        Unary(ExprUnary),
+        Lit(ExprLit),
+        Cast(ExprCast),
+        Type(ExprType),
+        If(ExprIf),
+        IfLet(ExprIfLet),
+        While(ExprWhile),
+        WhileLet(ExprWhileLet),
+        ForLoop(ExprForLoop),
+        Loop(ExprLoop),
+        Match(ExprMatch),
+        Closure(ExprClosure),
+        Unsafe(ExprUnsafe),
+        Block(ExprBlock),
+        Assign(ExprAssign),
+        AssignOp(ExprAssignOp),
+        Field(ExprField),
+        Index(ExprIndex),
+        Range(ExprRange),
+        Path(ExprPath),
+        Reference(ExprReference),
+        Break(ExprBreak),
+        Continue(ExprContinue),
+        Return(ExprReturn),
+        Macro(ExprMacro),
+        Struct(ExprStruct),
+        Repeat(ExprRepeat),
+        Paren(ExprParen),
+        Group(ExprGroup),
+        Try(ExprTry),
+        Catch(ExprCatch),
+        Yield(ExprYield),
+        Verbatim(ExprVerbatim),
+    }
+    enum Fields {
+        Named(FieldsNamed),
+        Unnamed(FieldsUnnamed),
+        Unit,
+    }
+    enum FnArg {
+        SelfRef(ArgSelfRef),
+        SelfValue(ArgSelf),
+        Captured(ArgCaptured),
+        Inferred(Pat),
+        Ignored(Type),
+    }
+    enum ForeignItem {
+        Fn(ForeignItemFn),
+        Static(ForeignItemStatic),
+        Type(ForeignItemType),
+        Verbatim(ForeignItemVerbatim),
+    }
+    enum GenericArgument {
+        Lifetime(Lifetime),
+        Type(Type),
+        Binding(Binding),
+        Const(Expr),
+    }
+    enum GenericMethodArgument {
+        Type(Type),
+        Const(Expr),
+    }
+    enum GenericParam {
+        Type(TypeParam),
+        Lifetime(LifetimeDef),
+        Const(ConstParam),
+    }
+    enum ImplItem {
+        Const(ImplItemConst),
+        Method(ImplItemMethod),
+        Type(ImplItemType),
+        Macro(ImplItemMacro),
+        Verbatim(ImplItemVerbatim),
+    }
+    enum Item {
+        ExternCrate(ItemExternCrate),
+        Use(ItemUse),
+        Static(ItemStatic),
+        Const(ItemConst),
+        Fn(ItemFn),
+        Mod(ItemMod),
+        ForeignMod(ItemForeignMod),
+        Type(ItemType),
+        Struct(ItemStruct),
+        Enum(ItemEnum),
+        Union(ItemUnion),
+        Trait(ItemTrait),
+        Impl(ItemImpl),
+        Macro(ItemMacro),
+        Macro2(ItemMacro2),
+        Verbatim(ItemVerbatim),
+    }
+    enum Lit {
+        Str(LitStr),
+        ByteStr(LitByteStr),
+        Byte(LitByte),
+        Char(LitChar),
+        Int(LitInt),
+        Float(LitFloat),
+        Bool(LitBool),
+        Verbatim(LitVerbatim),
+    }
+    enum MacroDelimiter {
+        Paren(Token),
+        Brace(Token),
+        Bracket(Token),
+    }
+    enum Member {
+        Named(Ident),
+        Unnamed(Index),
+    }
+    enum Meta {
+        Word(Ident),
+        List(MetaList),
+        NameValue(MetaNameValue),
+    }
+    enum NestedMeta {
+        Meta(Meta),
+        Literal(Lit),
+    }
+    enum Pat {
+        Wild(PatWild),
+        Ident(PatIdent),
+        Struct(PatStruct),
+        TupleStruct(PatTupleStruct),
+        Path(PatPath),
+        Tuple(PatTuple),
+        Box(PatBox),
+        Ref(PatRef),
+        Lit(PatLit),
+        Range(PatRange),
+        Slice(PatSlice),
+        Macro(PatMacro),
+        Verbatim(PatVerbatim),
+    }
+    enum PathArguments {
+        None,
+        AngleBracketed(AngleBracketedGenericArguments),
+        Parenthesized(ParenthesizedGenericArguments),
+    }
+    enum RangeLimits {
+        HalfOpen(Token),
+        Closed(Token),
+    }
+    enum ReturnType {
+        Default,
+        Type(Token, Type),
+    }
+    enum Stmt {
+        Local(Local),
+        Item(Item),
+        Expr(Expr),
+        Semi(Expr, Token),
+    }
+    enum TraitBoundModifier {
+        None,
+        Maybe(Token),
+    }
+    enum TraitItem {
+        Const(TraitItemConst),
+        Method(TraitItemMethod),
+        Type(TraitItemType),
+        Macro(TraitItemMacro),
+        Verbatim(TraitItemVerbatim),
+    }
+    enum Type {
+        Slice(TypeSlice),
+        Array(TypeArray),
+        Ptr(TypePtr),
+        Reference(TypeReference),
+        BareFn(TypeBareFn),
+        Never(TypeNever),
+        Tuple(TypeTuple),
+        Path(TypePath),
+        TraitObject(TypeTraitObject),
+        ImplTrait(TypeImplTrait),
+        Paren(TypeParen),
+        Group(TypeGroup),
+        Infer(TypeInfer),
+        Macro(TypeMacro),
+        Verbatim(TypeVerbatim),
+    }
+    enum TypeParamBound {
+        Trait(TraitBound),
+        Lifetime(Lifetime),
+    }
+    enum UnOp {
+        Deref(Token),
+        Not(Token),
+        Neg(Token),
+    }
+    enum UseTree {
+        Path(UsePath),
+        Name(UseName),
+        Rename(UseRename),
+        Glob(UseGlob),
+        Group(UseGroup),
+    }
+    enum Visibility {
+        Public(VisPublic),
+        Crate(VisCrate),
+        Restricted(VisRestricted),
+        Inherited,
+    }
+    enum WherePredicate {
+        Type(PredicateType),
+        Lifetime(PredicateLifetime),
+        Eq(PredicateEq),
+    }
+}
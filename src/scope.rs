@@ -0,0 +1,309 @@
+use document::Document;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use syn::{Ident, PatIdent, Path};
+use {join_path, walk, Collect, NodeKind, Queryable};
+
+/// Kinds of the items whose name is visible in the whole block, module or
+/// file declaring them.
+const ITEMS: &[NodeKind] = &[
+    NodeKind::ItemConst,
+    NodeKind::ItemEnum,
+    NodeKind::ItemFn,
+    NodeKind::ItemMod,
+    NodeKind::ItemStatic,
+    NodeKind::ItemStruct,
+    NodeKind::ItemTrait,
+    NodeKind::ItemType,
+    NodeKind::ItemUnion,
+];
+
+/// The definition each identifier of a document refers to, by id.
+///
+/// Only names written alone are resolved, `x` or `Point` but not `a::x`,
+/// and types and values share one namespace. A capitalized binding without
+/// `ref`, `mut` or `@`, such as `None`, is taken as a use of a constant or
+/// variant rather than a new variable.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Resolution {
+    definitions: HashMap<usize, usize>,
+}
+
+/// What the resolver reads from the nodes themselves, by path.
+#[derive(Default)]
+struct Facts {
+    names: HashMap<Vec<i64>, String>,
+    /// Bindings taken as uses of a constant.
+    constants: HashSet<Vec<i64>>,
+    /// Paths of a single segment without a leading `::`.
+    alone: HashSet<Vec<i64>>,
+}
+
+impl<'ast> Collect<'ast> for Facts {
+    fn collect<X: Queryable>(&mut self, node: &'ast X, base: &[i64], path: &[i64]) -> bool {
+        let node = node as &dyn Any;
+        let path = join_path(base, path);
+        if let Some(ident) = node.downcast_ref::<Ident>() {
+            self.names.insert(path, ident.to_string());
+        } else if let Some(pat) = node.downcast_ref::<PatIdent>() {
+            if pat.by_ref.is_none()
+                && pat.mutability.is_none()
+                && pat.subpat.is_none()
+                && pat.ident.to_string().starts_with(char::is_uppercase)
+            {
+                self.constants.insert(path);
+            }
+        } else if let Some(node) = node.downcast_ref::<Path>() {
+            if node.leading_colon.is_none() && node.segments.len() == 1 {
+                self.alone.insert(path);
+            }
+        }
+        true
+    }
+}
+
+impl Resolution {
+    pub(crate) fn new(document: &Document, root: &dyn Any) -> Resolution {
+        let facts: Facts = walk(
+            root,
+            Some(&[NodeKind::Ident, NodeKind::PatIdent, NodeKind::Path]),
+        );
+        let ids = |paths: Vec<&Vec<i64>>| -> HashSet<usize> {
+            paths
+                .into_iter()
+                .filter_map(|path| document.id(path))
+                .collect()
+        };
+        let mut resolver = Resolver {
+            document,
+            names: facts
+                .names
+                .iter()
+                .filter_map(|(path, name)| document.id(path).map(|id| (id, name.to_owned())))
+                .collect(),
+            constants: ids(facts.constants.iter().collect()),
+            alone: ids(facts.alone.iter().collect()),
+            scopes: Vec::new(),
+            definitions: HashMap::new(),
+        };
+        if !document.is_empty() {
+            resolver.walk(0);
+        }
+        Resolution {
+            definitions: resolver.definitions,
+        }
+    }
+    /// The identifier defining the one at `id`, itself for a definition.
+    pub(crate) fn definition(&self, id: usize) -> Option<usize> {
+        self.definitions.get(&id).cloned()
+    }
+    /// The identifiers referring to the definition at `id`, in document
+    /// order.
+    pub(crate) fn references(&self, id: usize) -> Vec<usize> {
+        let mut references: Vec<usize> = self
+            .definitions
+            .iter()
+            .filter(|&(&reference, &definition)| definition == id && reference != id)
+            .map(|(&reference, _)| reference)
+            .collect();
+        references.sort();
+        references
+    }
+}
+
+struct Scope {
+    /// The latest definition of each name, and whether it is an item.
+    names: HashMap<String, (usize, bool)>,
+    /// Set for the scope of an item: the items in it see the items
+    /// outside it, not the variables.
+    barrier: bool,
+}
+
+struct Resolver<'a> {
+    document: &'a Document,
+    names: HashMap<usize, String>,
+    constants: HashSet<usize>,
+    alone: HashSet<usize>,
+    scopes: Vec<Scope>,
+    definitions: HashMap<usize, usize>,
+}
+
+impl<'a> Resolver<'a> {
+    fn fields(&self, id: usize, field: &str) -> Vec<usize> {
+        self.document[id]
+            .children
+            .iter()
+            .cloned()
+            .filter(|&child| {
+                self.document[child]
+                    .step
+                    .is_some_and(|step| step.field == field)
+            })
+            .collect()
+    }
+    fn name(&self, id: usize) -> Option<String> {
+        self.names.get(&id).cloned()
+    }
+    fn scoped<F: FnOnce(&mut Self)>(&mut self, barrier: bool, f: F) {
+        self.scopes.push(Scope {
+            names: HashMap::new(),
+            barrier,
+        });
+        f(self);
+        self.scopes.pop();
+    }
+    fn define(&mut self, ident: usize, item: bool) {
+        if let (Some(name), Some(scope)) = (self.name(ident), self.scopes.last_mut()) {
+            scope.names.insert(name, (ident, item));
+            self.definitions.insert(ident, ident);
+        }
+    }
+    fn refer(&mut self, ident: usize) {
+        let name = match self.name(ident) {
+            Some(name) => name,
+            None => return,
+        };
+        let mut items_only = false;
+        for scope in self.scopes.iter().rev() {
+            match scope.names.get(&name) {
+                Some(&(definition, item)) if item || !items_only => {
+                    self.definitions.insert(ident, definition);
+                    return;
+                }
+                _ => items_only |= scope.barrier,
+            }
+        }
+    }
+    /// Defines the items declared directly in the node at `id`.
+    fn hoist(&mut self, id: usize) {
+        let document = self.document;
+        for &child in &document[id].children {
+            let kind = self.document[child].kind;
+            if ITEMS.contains(&kind) {
+                for ident in self.fields(child, "ident") {
+                    self.define(ident, true);
+                }
+            } else if kind == NodeKind::Stmt || kind == NodeKind::Item {
+                self.hoist(child);
+            }
+        }
+    }
+    fn walk_children<P: Fn(&str) -> bool>(&mut self, id: usize, predicate: P) {
+        let document = self.document;
+        for &child in &document[id].children {
+            if self.document[child]
+                .step
+                .is_none_or(|step| predicate(step.field))
+            {
+                self.walk(child);
+            }
+        }
+    }
+    fn walk_fields(&mut self, id: usize, field: &str) {
+        for child in self.fields(id, field) {
+            self.walk(child);
+        }
+    }
+    fn bind_fields(&mut self, id: usize, field: &str) {
+        for child in self.fields(id, field) {
+            self.bind(child);
+        }
+    }
+    /// Walks a pattern, defining the variables it binds.
+    fn bind(&mut self, id: usize) {
+        let document = self.document;
+        let entry = &document[id];
+        match entry.kind {
+            NodeKind::PatIdent => {
+                let constant = self.constants.contains(&id);
+                for ident in self.fields(id, "ident") {
+                    if constant {
+                        self.refer(ident);
+                    } else {
+                        self.define(ident, false);
+                    }
+                }
+                self.bind_fields(id, "subpat");
+            }
+            NodeKind::Path => self.walk(id),
+            _ => {
+                for &child in &entry.children {
+                    self.bind(child);
+                }
+            }
+        }
+    }
+    fn walk(&mut self, id: usize) {
+        let kind = self.document[id].kind;
+        match kind {
+            NodeKind::File
+            | NodeKind::ItemMod
+            | NodeKind::ItemImpl
+            | NodeKind::ItemTrait
+            | NodeKind::Block => self.scoped(kind != NodeKind::Block, |this| {
+                this.hoist(id);
+                this.walk_children(id, |_| true);
+            }),
+            NodeKind::ItemFn
+            | NodeKind::ImplItemMethod
+            | NodeKind::TraitItemMethod
+            | NodeKind::ItemConst
+            | NodeKind::ItemEnum
+            | NodeKind::ItemStatic
+            | NodeKind::ItemStruct
+            | NodeKind::ItemType
+            | NodeKind::ItemUnion => self.scoped(true, |this| this.walk_children(id, |_| true)),
+            NodeKind::FnDecl => {
+                self.walk_children(id, |field| field != "inputs" && field != "output");
+                self.bind_fields(id, "inputs");
+                self.walk_fields(id, "output");
+            }
+            NodeKind::ExprClosure => self.scoped(false, |this| {
+                this.bind_fields(id, "inputs");
+                this.walk_children(id, |field| field != "inputs");
+            }),
+            NodeKind::Local => {
+                self.walk_children(id, |field| field != "pats");
+                self.bind_fields(id, "pats");
+            }
+            NodeKind::Arm => self.scoped(false, |this| {
+                this.bind_fields(id, "pats");
+                this.walk_children(id, |field| field != "pats");
+            }),
+            NodeKind::ExprIfLet | NodeKind::ExprWhileLet | NodeKind::ExprForLoop => {
+                self.walk_fields(id, "expr");
+                self.scoped(false, |this| {
+                    this.bind_fields(id, "pats");
+                    this.bind_fields(id, "pat");
+                    this.walk_fields(id, "then_branch");
+                    this.walk_fields(id, "body");
+                });
+                self.walk_fields(id, "else_branch");
+            }
+            NodeKind::TypeParam => {
+                for ident in self.fields(id, "ident") {
+                    self.define(ident, false);
+                }
+                self.walk_children(id, |field| field != "ident");
+            }
+            NodeKind::Path => {
+                let alone = self.alone.contains(&id);
+                let named = self.document[id].parent.is_none_or(|parent| {
+                    !matches!(
+                        self.document[parent].kind,
+                        NodeKind::Attribute | NodeKind::Macro | NodeKind::VisRestricted
+                    )
+                });
+                if alone && named {
+                    for segment in self.fields(id, "segments") {
+                        for ident in self.fields(segment, "ident") {
+                            self.refer(ident);
+                        }
+                    }
+                }
+                self.walk_children(id, |_| true);
+            }
+            _ => self.walk_children(id, |_| true),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::any::Any;
+use std::cell::OnceCell;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+use {accept_root, join_path, kind_of_type, Collect, Node, NodeKind, Query, Queryable};
+
+/// The nodes of a `QueryResult`. Those of a `query` are only searched for
+/// when first needed, so that `find` can stop at the first match instead.
+#[derive(Clone)]
+pub(crate) struct Selection<T> {
+    nodes: OnceCell<Vec<Node<T>>>,
+    search: Option<Search>,
+}
+
+/// Source nodes with their paths.
+type Roots<'a> = Vec<(&'a dyn Any, &'a [i64])>;
+
+/// The nodes a pending `query` searches under.
+#[derive(Clone)]
+struct Search {
+    sources: Rc<dyn Any>,
+    /// Each source node with its path, in document order, leaving out the
+    /// ones inside another so that no node is found twice.
+    roots: fn(&dyn Any) -> Roots<'_>,
+}
+
+fn whole(root: &dyn Any) -> Roots<'_> {
+    vec![(root, &[])]
+}
+
+fn roots<X: Queryable>(sources: &dyn Any) -> Roots<'_> {
+    let mut nodes: Vec<&Node<X>> = sources
+        .downcast_ref::<Selection<X>>()
+        .map_or_else(Vec::new, |sources| sources.iter().collect());
+    nodes.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut covered: Option<&[i64]> = None;
+    let mut roots: Roots = Vec::new();
+    for node in nodes {
+        if covered.is_some_and(|path| node.path.starts_with(path)) {
+            continue;
+        }
+        covered = Some(&node.path);
+        roots.push((&node.data, &node.path));
+    }
+    roots
+}
+
+impl Search {
+    /// Runs a query under every root until `limit` nodes are collected.
+    fn run<'a, C: Collect<'a>>(&'a self, results: C, limit: Option<usize>) -> C {
+        let mut query = Query::with_results(Vec::new(), None, None, results);
+        query.limit = limit;
+        for (node, path) in (self.roots)(&*self.sources) {
+            query.base = path.to_vec();
+            query.path.clear();
+            accept_root(&mut query, node);
+            if query.limit.is_some_and(|limit| query.found >= limit) {
+                break;
+            }
+        }
+        query.results
+    }
+}
+
+/// The first node accepted by `predicate`.
+struct Find<T, F> {
+    predicate: F,
+    found: Option<Node<T>>,
+}
+
+impl<'ast, T: Queryable, F: FnMut(&Node<T>) -> bool> Collect<'ast> for Find<T, F> {
+    fn collect<X: Queryable>(&mut self, node: &'ast X, base: &[i64], path: &[i64]) -> bool {
+        let found = T::downcast_from(node).map(|data| Node {
+            data,
+            path: join_path(base, path),
+            location: node.location(),
+        });
+        match found {
+            Some(found) if (self.predicate)(&found) => {
+                self.found = Some(found);
+                true
+            }
+            _ => false,
+        }
+    }
+    fn wants(&self, kind: NodeKind) -> bool {
+        kind_of_type::<T>() == Some(kind)
+    }
+    fn collect_owned<X: Queryable>(&mut self, node: X, base: &[i64], path: &[i64]) -> bool {
+        Collect::collect(self, &node, base, path)
+    }
+}
+
+impl<T: Queryable> Selection<T> {
+    pub(crate) fn new(nodes: Vec<Node<T>>) -> Selection<T> {
+        Selection {
+            nodes: OnceCell::from(nodes),
+            search: None,
+        }
+    }
+    /// The nodes under `root`.
+    pub(crate) fn root(root: Rc<dyn Any>) -> Selection<T> {
+        Selection {
+            nodes: OnceCell::new(),
+            search: Some(Search {
+                sources: root,
+                roots: whole,
+            }),
+        }
+    }
+    /// The nodes under those of `sources`.
+    pub(crate) fn query<X: Queryable>(sources: &Rc<Selection<X>>) -> Selection<T> {
+        Selection {
+            nodes: OnceCell::new(),
+            search: Some(Search {
+                sources: sources.clone(),
+                roots: roots::<X>,
+            }),
+        }
+    }
+    fn nodes(&self) -> &Vec<Node<T>> {
+        self.nodes.get_or_init(|| match self.search {
+            Some(ref search) => search.run(Vec::new(), None),
+            None => Vec::new(),
+        })
+    }
+    pub(crate) fn nodes_mut(&mut self) -> &mut Vec<Node<T>> {
+        self.nodes();
+        self.search = None;
+        self.nodes.get_mut().expect("the nodes were just found")
+    }
+    pub(crate) fn into_nodes(self) -> Vec<Node<T>> {
+        self.nodes();
+        self.nodes.into_inner().unwrap_or_default()
+    }
+    /// The first node accepted by `predicate`. When the nodes have not been
+    /// searched for yet, the search stops there.
+    pub(crate) fn find<F>(&self, mut predicate: F) -> Option<Node<T>>
+    where
+        F: FnMut(&Node<T>) -> bool,
+    {
+        match (self.nodes.get(), &self.search) {
+            (None, Some(search)) => {
+                search
+                    .run(
+                        Find {
+                            predicate,
+                            found: None,
+                        },
+                        Some(1),
+                    )
+                    .found
+            }
+            _ => self.iter().find(|node| predicate(node)).cloned(),
+        }
+    }
+}
+
+impl<T: Queryable> Deref for Selection<T> {
+    type Target = Vec<Node<T>>;
+    fn deref(&self) -> &Vec<Node<T>> {
+        self.nodes()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Selection<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.nodes.get() {
+            Some(nodes) => f.debug_list().entries(nodes).finish(),
+            None => f.write_str("[..]"),
+        }
+    }
+}
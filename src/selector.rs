@@ -0,0 +1,244 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::slice;
+use std::str::FromStr;
+use {DynNode, Node, NodeKind, QueryResult, Queryable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+    Adjacent,
+    Sibling,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+    combinator: Combinator,
+    kind: Option<NodeKind>,
+}
+
+/// A parsed selector such as `ItemFn > Block ExprMethodCall, ImplItemMethod`.
+///
+/// Supported combinators are descendant (whitespace), child (`>`),
+/// adjacent sibling (`+`) and general sibling (`~`); `*` matches any node
+/// and `,` separates alternatives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    groups: Vec<Vec<Step>>,
+}
+
+/// Error returned when a selector fails to parse, `column` is 1-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl SelectorError {
+    fn new<S: Into<String>>(column: usize, message: S) -> SelectorError {
+        SelectorError {
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl Error for SelectorError {}
+
+impl Step {
+    fn matches(&self, node: &Node<DynNode>) -> bool {
+        self.kind.is_none_or(|kind| node.kind() == kind)
+    }
+    fn apply<R: Queryable>(&self, context: &QueryResult<DynNode, R>) -> QueryResult<DynNode, R> {
+        let candidates = match self.combinator {
+            Combinator::Descendant => self.descendants(context, None),
+            Combinator::Child => self.descendants(context, Some(1)),
+            Combinator::Adjacent => context.next(),
+            Combinator::Sibling => context.entries(context.document().following(context.paths())),
+        };
+        candidates.filter(|node| self.matches(node))
+    }
+    fn descendants<R: Queryable>(
+        &self,
+        context: &QueryResult<DynNode, R>,
+        deep: Option<usize>,
+    ) -> QueryResult<DynNode, R> {
+        let kinds = self.kind.as_ref().map(slice::from_ref);
+        let mut result = BTreeSet::new();
+        for node in context.iter() {
+            for child in node
+                .data
+                .visit_kinds::<DynNode>(node.path.to_owned(), deep, kinds)
+            {
+                if child.path != node.path {
+                    result.insert(child);
+                }
+            }
+        }
+        context.with_nodes(result.into_iter().collect())
+    }
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Result<Selector, SelectorError> {
+        let chars: Vec<char> = selector.chars().collect();
+        let mut groups = Vec::new();
+        let mut group: Vec<Step> = Vec::new();
+        let mut pending: Option<(Combinator, usize)> = None;
+        let mut space = false;
+        let mut index = 0;
+        while index < chars.len() {
+            let column = index + 1;
+            let c = chars[index];
+            if c.is_whitespace() {
+                space = true;
+                index += 1;
+                continue;
+            }
+            let combinator = match c {
+                '>' => Some(Combinator::Child),
+                '+' => Some(Combinator::Adjacent),
+                '~' => Some(Combinator::Sibling),
+                _ => None,
+            };
+            if let Some(combinator) = combinator {
+                if group.is_empty() || pending.is_some() {
+                    return Err(SelectorError::new(
+                        column,
+                        format!("expected a node kind before `{}`", c),
+                    ));
+                }
+                pending = Some((combinator, column));
+                space = false;
+                index += 1;
+                continue;
+            }
+            if c == ',' {
+                if let Some((_, column)) = pending {
+                    return Err(SelectorError::new(
+                        column,
+                        "expected a node kind after combinator",
+                    ));
+                }
+                if group.is_empty() {
+                    return Err(SelectorError::new(
+                        column,
+                        "expected a node kind before `,`",
+                    ));
+                }
+                groups.push(group);
+                group = Vec::new();
+                space = false;
+                index += 1;
+                continue;
+            }
+            let kind = if c == '*' {
+                index += 1;
+                None
+            } else if c.is_alphabetic() || c == '_' {
+                let start = index;
+                while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_')
+                {
+                    index += 1;
+                }
+                let name: String = chars[start..index].iter().collect();
+                match NodeKind::from_name(&name) {
+                    Some(kind) => Some(kind),
+                    None => {
+                        return Err(SelectorError::new(
+                            column,
+                            format!("unknown node kind `{}`", name),
+                        ))
+                    }
+                }
+            } else {
+                return Err(SelectorError::new(
+                    column,
+                    format!("unexpected character `{}`", c),
+                ));
+            };
+            let combinator = match pending.take() {
+                Some((combinator, _)) => combinator,
+                None if group.is_empty() || space => Combinator::Descendant,
+                None => return Err(SelectorError::new(column, "expected a combinator")),
+            };
+            group.push(Step { combinator, kind });
+            space = false;
+        }
+        if let Some((_, column)) = pending {
+            return Err(SelectorError::new(
+                column,
+                "expected a node kind after combinator",
+            ));
+        }
+        if group.is_empty() {
+            return Err(SelectorError::new(chars.len() + 1, "expected a node kind"));
+        }
+        groups.push(group);
+        Ok(Selector { groups })
+    }
+    /// Evaluates the selector with every node of `context` as a starting point.
+    ///
+    /// The leading kind of each alternative matches like `find`, so the
+    /// context nodes themselves are candidates; the results are deduplicated
+    /// and in document order.
+    pub fn apply<T: Queryable, R: Queryable>(
+        &self,
+        context: &QueryResult<T, R>,
+    ) -> QueryResult<DynNode, R> {
+        let mut result = BTreeSet::new();
+        for group in &self.groups {
+            let mut steps = group.iter();
+            let first = steps.next().expect("selector groups are never empty");
+            let mut current = match first.kind {
+                Some(kind) => context.query_any(&[kind]),
+                None => context.query_dyn(),
+            };
+            for step in steps {
+                current = step.apply(&current);
+            }
+            result.extend(current);
+        }
+        context.with_nodes(result.into_iter().collect())
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+    fn from_str(s: &str) -> Result<Selector, SelectorError> {
+        Selector::parse(s)
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, group) in self.groups.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            for (j, step) in group.iter().enumerate() {
+                if j > 0 {
+                    match step.combinator {
+                        Combinator::Descendant => write!(f, " ")?,
+                        Combinator::Child => write!(f, " > ")?,
+                        Combinator::Adjacent => write!(f, " + ")?,
+                        Combinator::Sibling => write!(f, " ~ ")?,
+                    }
+                }
+                match step.kind {
+                    Some(kind) => write!(f, "{}", kind)?,
+                    None => write!(f, "*")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
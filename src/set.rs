@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Error returned when combining query results whose roots are not known
+/// to be the same, so that their paths cannot be compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootMismatch {
+    /// The roots differ in kind or in tokens.
+    Different,
+    /// The roots are not shared and print no tokens to compare, as a
+    /// `Span` root.
+    Incomparable,
+}
+
+impl fmt::Display for RootMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            RootMismatch::Different => "query results have different roots",
+            RootMismatch::Incomparable => "query results have roots that cannot be compared",
+        })
+    }
+}
+
+impl Error for RootMismatch {}
+
+/// Nodes of `left` and `right`, keyed by `key` and kept according to
+/// whether they occur in each side; a node in both is taken from `left`.
+pub(crate) fn combine<N, K, F, P>(left: &[N], right: &[N], key: F, keep: P) -> Vec<N>
+where
+    N: Clone,
+    K: Ord,
+    F: Fn(&N) -> K,
+    P: Fn(bool, bool) -> bool,
+{
+    let mut nodes = BTreeMap::new();
+    for node in right {
+        nodes.insert(key(node), (node, false, true));
+    }
+    for node in left {
+        let entry = nodes.entry(key(node)).or_insert((node, true, false));
+        entry.0 = node;
+        entry.1 = true;
+    }
+    nodes
+        .into_iter()
+        .filter(|&(_, (_, in_left, in_right))| keep(in_left, in_right))
+        .map(|(_, (node, _, _))| node.to_owned())
+        .collect()
+}
@@ -0,0 +1,327 @@
+use proc_macro2::{Group, Ident, Literal, Punct, Spacing, TokenStream, TokenTree};
+use render;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::iter;
+use std::ops::{Bound, Index, Range};
+use std::rc::Rc;
+use {Location, Node};
+
+/// A token type `TokenResult` can select: `TokenTree`, or one of its
+/// `Group`, `Punct`, `Literal` and `Ident` variants.
+pub trait FromTokenTree: Sized + Clone + 'static {
+    fn from_tree(tree: &TokenTree) -> Option<Self>;
+}
+
+impl FromTokenTree for TokenTree {
+    fn from_tree(tree: &TokenTree) -> Option<TokenTree> {
+        Some(tree.to_owned())
+    }
+}
+
+macro_rules! from_token_tree {
+    ($($variant:ident),*) => {
+        $(
+            impl FromTokenTree for $variant {
+                fn from_tree(tree: &TokenTree) -> Option<$variant> {
+                    match *tree {
+                        TokenTree::$variant(ref token) => Some(token.to_owned()),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_token_tree!(Group, Punct, Literal, Ident);
+
+/// Querying of token streams that are not valid syntax, such as the
+/// arguments of an attribute or the body of a macro. A token's path holds
+/// its index in the stream, preceded by the path of its `Group`.
+pub trait TokenQueryable {
+    fn token_stream(&self) -> TokenStream;
+    fn query<U: FromTokenTree>(&self) -> TokenResult<U> {
+        TokenResult::root(self.token_stream()).query()
+    }
+    fn find<U: FromTokenTree>(&self) -> TokenResult<U> {
+        self.query()
+    }
+    fn children<U: FromTokenTree>(&self) -> TokenResult<U> {
+        TokenResult::root(self.token_stream()).children()
+    }
+}
+
+impl TokenQueryable for TokenStream {
+    fn token_stream(&self) -> TokenStream {
+        self.to_owned()
+    }
+}
+
+impl TokenQueryable for Group {
+    fn token_stream(&self) -> TokenStream {
+        self.stream()
+    }
+}
+
+/// Every token of a stream by path, built once and shared by the results
+/// derived from it.
+#[derive(Debug, Default)]
+struct Trees {
+    trees: BTreeMap<Vec<i64>, TokenTree>,
+    /// The number of tokens in the stream and in each group.
+    lens: HashMap<Vec<i64>, i64>,
+}
+
+impl Trees {
+    fn index(&mut self, base: &[i64], stream: TokenStream) {
+        let mut len = 0;
+        for (i, tree) in stream.into_iter().enumerate() {
+            let mut path = base.to_vec();
+            path.push(i as i64);
+            if let TokenTree::Group(ref group) = tree {
+                self.index(&path, group.stream());
+            }
+            self.trees.insert(path, tree);
+            len = i as i64 + 1;
+        }
+        self.lens.insert(base.to_vec(), len);
+    }
+    /// The paths of the tokens in `range` of the stream or group at `parent`.
+    fn children(&self, parent: &[i64], range: Range<i64>) -> impl Iterator<Item = Vec<i64>> + '_ {
+        let len = self.lens.get(parent).cloned().unwrap_or(0);
+        let parent = parent.to_vec();
+        (range.start.max(0)..range.end.min(len)).map(move |i| {
+            let mut path = parent.to_owned();
+            path.push(i);
+            path
+        })
+    }
+    /// The paths of the token at `base`, if any, and of the tokens inside it.
+    fn subtree<'a>(&'a self, base: &'a [i64]) -> impl Iterator<Item = &'a Vec<i64>> + 'a {
+        self.trees
+            .range::<[i64], _>((Bound::Included(base), Bound::Unbounded))
+            .map(|(path, _)| path)
+            .take_while(move |path| path.starts_with(base))
+    }
+}
+
+/// The path of the group around `path` and its index in it.
+fn split(path: &[i64]) -> (&[i64], i64) {
+    match path.split_last() {
+        Some((&last, parent)) => (parent, last),
+        None => (path, 0),
+    }
+}
+
+/// Tokens selected from a stream, in the manner of `QueryResult`.
+#[derive(Debug, Clone)]
+pub struct TokenResult<T> {
+    nodes: Vec<Node<T>>,
+    trees: Rc<Trees>,
+    /// Set for the stream itself, which is not a token.
+    root: bool,
+}
+
+impl<T: FromTokenTree> Index<usize> for TokenResult<T> {
+    type Output = Node<T>;
+    fn index(&self, id: usize) -> &Node<T> {
+        &self.nodes[id]
+    }
+}
+
+impl TokenResult<TokenTree> {
+    /// A result holding no token, whose children are the tokens of
+    /// `stream`.
+    fn root(stream: TokenStream) -> TokenResult<TokenTree> {
+        let mut trees = Trees::default();
+        trees.index(&[], stream);
+        TokenResult {
+            nodes: Vec::new(),
+            trees: Rc::new(trees),
+            root: true,
+        }
+    }
+}
+
+impl<T: FromTokenTree> TokenResult<T> {
+    /// The tokens at `paths` that are `U`s.
+    fn with<U: FromTokenTree>(&self, paths: BTreeSet<Vec<i64>>) -> TokenResult<U> {
+        let nodes = paths
+            .into_iter()
+            .filter_map(|path| {
+                let tree = self.trees.trees.get(&path)?;
+                U::from_tree(tree).map(|data| Node {
+                    data,
+                    path,
+                    location: Some(Location::new(tree.span(), tree.span())),
+                })
+            })
+            .collect();
+        TokenResult {
+            nodes,
+            trees: self.trees.clone(),
+            root: false,
+        }
+    }
+    /// The paths under which `query` and `children` look.
+    fn bases(&self) -> Vec<&[i64]> {
+        if self.root {
+            return vec![&[]];
+        }
+        self.iter().map(|node| node.path.as_slice()).collect()
+    }
+    /// The selected tokens and the tokens inside them, as `QueryResult::query`
+    /// does.
+    pub fn query<U: FromTokenTree>(&self) -> TokenResult<U> {
+        let mut bases = self.bases();
+        bases.sort();
+        let mut paths = BTreeSet::new();
+        let mut covered: Option<&[i64]> = None;
+        for base in bases {
+            if covered.is_some_and(|path| base.starts_with(path)) {
+                continue;
+            }
+            covered = Some(base);
+            paths.extend(self.trees.subtree(base).cloned());
+        }
+        self.with(paths)
+    }
+    pub fn find<U: FromTokenTree>(&self) -> TokenResult<U> {
+        self.query()
+    }
+    pub fn children<U: FromTokenTree>(&self) -> TokenResult<U> {
+        let bases = self.bases();
+        let paths = bases
+            .into_iter()
+            .flat_map(|base| self.trees.children(base, 0..i64::MAX))
+            .collect();
+        self.with(paths)
+    }
+    /// The `Group` around each token.
+    pub fn parent<U: FromTokenTree>(&self) -> TokenResult<U> {
+        let paths = self
+            .iter()
+            .filter(|node| node.path.len() > 1)
+            .map(|node| split(&node.path).0.to_vec())
+            .collect();
+        self.with(paths)
+    }
+    /// The tokens in the group of each node in the two ranges of indices
+    /// that `range` gives for the node's own.
+    fn siblings_in<U: FromTokenTree, F>(&self, range: F) -> TokenResult<U>
+    where
+        F: Fn(i64) -> [Range<i64>; 2],
+    {
+        let mut paths = BTreeSet::new();
+        for node in self.iter() {
+            let (parent, index) = split(&node.path);
+            for range in range(index) {
+                paths.extend(self.trees.children(parent, range));
+            }
+        }
+        self.with(paths)
+    }
+    pub fn prev<U: FromTokenTree>(&self) -> TokenResult<U> {
+        self.siblings_in(|i| [i - 1..i, 0..0])
+    }
+    pub fn prev_all<U: FromTokenTree>(&self) -> TokenResult<U> {
+        self.siblings_in(|i| [0..i, 0..0])
+    }
+    pub fn next<U: FromTokenTree>(&self) -> TokenResult<U> {
+        self.siblings_in(|i| [i + 1..i + 2, 0..0])
+    }
+    pub fn next_all<U: FromTokenTree>(&self) -> TokenResult<U> {
+        self.siblings_in(|i| [i + 1..i64::MAX, 0..0])
+    }
+    pub fn siblings<U: FromTokenTree>(&self) -> TokenResult<U> {
+        self.siblings_in(|i| [0..i, i + 1..i64::MAX])
+    }
+    /// The selected `Punct`s that start a sequence of joint punctuation
+    /// spelling `op`, such as `=>` or `::`.
+    pub fn ops(&self, op: &str) -> TokenResult<Punct> {
+        let paths = self
+            .iter()
+            .filter(|node| self.spells(&node.path, op))
+            .map(|node| node.path.to_owned())
+            .collect();
+        self.with(paths)
+    }
+    fn spells(&self, path: &[i64], op: &str) -> bool {
+        let punct = |path: &[i64]| match self.trees.trees.get(path) {
+            Some(TokenTree::Punct(punct)) => Some(punct.to_owned()),
+            _ => None,
+        };
+        let mut path = path.to_vec();
+        let last = path.len() - 1;
+        if path[last] > 0 {
+            path[last] -= 1;
+            if punct(&path).is_some_and(|prev| prev.spacing() == Spacing::Joint) {
+                return false;
+            }
+            path[last] += 1;
+        }
+        let mut chars = op.chars().peekable();
+        while let Some(c) = chars.next() {
+            let spacing = match punct(&path) {
+                Some(ref punct) if punct.as_char() == c => punct.spacing(),
+                _ => return false,
+            };
+            let joint = chars.peek().is_some();
+            if joint != (spacing == Spacing::Joint) {
+                return false;
+            }
+            path[last] += 1;
+        }
+        true
+    }
+    pub fn filter<P>(&self, mut predicate: P) -> TokenResult<T>
+    where
+        for<'r> P: FnMut(&'r Node<T>) -> bool,
+    {
+        TokenResult {
+            nodes: self
+                .iter()
+                .filter(|node| predicate(node))
+                .cloned()
+                .collect(),
+            trees: self.trees.clone(),
+            root: false,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, Node<T>> {
+        self.nodes.iter()
+    }
+    pub fn first(&self) -> Option<Node<T>> {
+        self.nodes.first().cloned()
+    }
+    pub fn last(&self) -> Option<Node<T>> {
+        self.nodes.last().cloned()
+    }
+    /// The tokens printed as by `Render::Tokens`, a group with its
+    /// delimiters.
+    pub fn texts(&self) -> Vec<String> {
+        self.iter()
+            .map(|node| {
+                let tree = self.trees.trees[&node.path].to_owned();
+                render::print(iter::once(tree).collect(), false)
+            })
+            .collect()
+    }
+    pub fn locations(&self) -> Vec<Option<Location>> {
+        self.iter().map(|node| node.location).collect()
+    }
+}
+
+impl<T: FromTokenTree> IntoIterator for TokenResult<T> {
+    type Item = Node<T>;
+    type IntoIter = ::std::vec::IntoIter<Node<T>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.nodes.into_iter()
+    }
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "cli")]
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+const FIXTURE: &str = "tests/fixtures/cli.rs";
+
+#[test]
+fn synq() {
+    let output = Command::new(env!("CARGO_BIN_EXE_synq"))
+        .args(["ItemFn > Block", FIXTURE])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().next(),
+        Some("tests/fixtures/cli.rs:3:20: fn answer() -> u32 {")
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_synq"))
+        .args(["ReturnType", FIXTURE])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            "tests/fixtures/cli.rs:3:13: fn answer() -> u32 {",
+            "tests/fixtures/cli.rs:?:?: ReturnType",
+        ]
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_synq"))
+        .args(["--json", "ReturnType", FIXTURE])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().nth(1).unwrap().contains("\"line\":null"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_synq"))
+        .args(["--count", "--json", "ItemUnion", FIXTURE])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.lines().next(),
+        Some("{\"file\":\"tests/fixtures/cli.rs\",\"count\":0}")
+    );
+}
+
+#[test]
+fn synq_walk() {
+    let dir = env::temp_dir().join(format!("synq-walk-{}", std::process::id()));
+    for sub in ["src", "target", ".git"] {
+        fs::create_dir_all(dir.join(sub)).unwrap();
+        fs::write(dir.join(sub).join("a.rs"), "fn a() {}").unwrap();
+    }
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&dir, dir.join("src/again")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_synq"))
+        .args(["--count", "ItemFn"])
+        .arg(&dir)
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let expected = format!("{}:1\n", dir.join("src").join("a.rs").display());
+    assert_eq!(stdout, expected);
+}
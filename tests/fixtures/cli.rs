@@ -0,0 +1,7 @@
+// Searched by tests/cli.rs.
+
+fn answer() -> u32 {
+    42
+}
+
+fn unit() {}
@@ -0,0 +1,4 @@
+#[path = "lib.rs"]
+mod back;
+
+fn in_a() {}
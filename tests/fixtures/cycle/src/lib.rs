@@ -0,0 +1,6 @@
+mod a;
+
+#[path = "lib.rs"]
+mod again;
+
+fn root() {}
@@ -0,0 +1,5 @@
+//! Module a.
+
+mod b;
+
+pub fn in_a() {}
@@ -0,0 +1 @@
+fn in_b() {}
@@ -0,0 +1,15 @@
+//! Root.
+
+mod a;
+mod c;
+#[path = "other/d.rs"]
+mod d;
+mod inline {
+    mod e;
+    #[path = "f.rs"]
+    mod f;
+}
+#[cfg(windows)]
+mod missing;
+
+fn root() {}
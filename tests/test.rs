@@ -1,9 +1,11 @@
+#![allow(clippy::bool_assert_comparison)]
+
 extern crate proc_macro2;
 extern crate syn;
 extern crate syn_query;
 use proc_macro2::Span;
 use syn::{ExprStruct, FieldValue, Ident};
-use syn_query::Queryable;
+use syn_query::{DocComment, MutateError, NodeKind, NodePath, Queryable, Render, RootMismatch};
 
 #[test]
 fn find() {
@@ -146,6 +148,32 @@ fn siblings() {
     assert_eq!(qr.find::<Ident>()[1].data, "y");
 }
 
+#[test]
+fn sibling_axes_of_several_nodes() {
+    let s = "fn f() { a(); b(); c(); d(); e(); }";
+    let item: syn::ItemFn = syn::parse_str(s).unwrap();
+    let stmts = item.query::<syn::Stmt>();
+    let named = |names: &'static [&'static str]| {
+        move |node: &syn_query::Node<syn::Stmt>| names.contains(&node.to_source().as_str())
+    };
+
+    let selected = stmts.filter(named(&["b();", "d();"]));
+    assert_eq!(selected.prev_all::<syn::Stmt>().texts(), vec!["a();"]);
+    assert_eq!(selected.next_all::<syn::Stmt>().texts(), vec!["e();"]);
+
+    let last = stmts.filter(named(&["e();"]));
+    let qr = last.prev_until::<syn::Stmt, _>(named(&["b();", "c();"]));
+    assert_eq!(qr.texts(), vec!["c();", "d();"]);
+    let first = stmts.filter(named(&["a();"]));
+    let qr = first.next_until::<syn::Stmt, _>(named(&["c();", "d();"]));
+    assert_eq!(qr.texts(), vec!["b();", "c();"]);
+
+    let borrowed = selected.as_borrowed();
+    assert_eq!(borrowed.prev_all::<syn::Stmt>().len(), 1);
+    assert_eq!(borrowed.next_all::<syn::Stmt>().len(), 1);
+    assert_eq!(item.select("Stmt ~ Stmt").unwrap().len(), 4);
+}
+
 #[test]
 fn first() {
     let s = "Point { x: 1, y: 1 }";
@@ -212,3 +240,1162 @@ fn not() {
     assert_eq!(qr.len(), 2);
     assert_eq!(qr[1].data, "y");
 }
+
+#[test]
+fn select() {
+    let s = "fn main() { let v = foo.bar(); v.baz(); } fn other() { qux.quux(); }";
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let qr = file.select("ItemFn > Block ExprMethodCall").unwrap();
+    assert_eq!(qr.len(), 3);
+    assert_eq!(qr[0].data.name(), "ExprMethodCall");
+    assert_eq!(qr.find::<Ident>()[1].data, "bar");
+
+    let qr = file.select("ItemFn > Ident").unwrap();
+    assert_eq!(qr.len(), 2);
+    assert_eq!(qr[1].data.find::<Ident>()[0].data, "other");
+
+    let qr = file.select("Block > Stmt + Stmt").unwrap();
+    assert_eq!(qr.len(), 1);
+
+    let qr = file.select("Item ~ Item, Local").unwrap();
+    assert_eq!(qr.len(), 2);
+    assert_eq!(qr[0].data.name(), "Local");
+    assert_eq!(qr[1].data.name(), "Item");
+
+    let s = "Point { x: 1, y: 1 }";
+    let st: ExprStruct = syn::parse_str(s).unwrap();
+    let qr = st.find::<FieldValue>()
+        .select("Member > *")
+        .unwrap()
+        .find::<Ident>();
+    assert_eq!(qr.len(), 2);
+    assert_eq!(qr[1].data, "y");
+}
+
+#[test]
+fn select_error() {
+    let s = "Point { x: 1, y: 1 }";
+    let st: ExprStruct = syn::parse_str(s).unwrap();
+
+    let err = st.select("ItemFn > ").unwrap_err();
+    assert_eq!(err.column, 8);
+    let err = st.select("ItemFn Blok").unwrap_err();
+    assert_eq!(err.column, 8);
+    assert_eq!(err.message, "unknown node kind `Blok`");
+    let err = st.select("ItemFn,").unwrap_err();
+    assert_eq!(err.column, 8);
+    let err = st.select("> Block").unwrap_err();
+    assert_eq!(err.column, 1);
+    let err = st.select("Block $").unwrap_err();
+    assert_eq!(err.to_string(), "unexpected character `$` at column 7");
+}
+
+#[test]
+fn kind() {
+    let s = "Point { x: 1, y: 1 }";
+    let st: ExprStruct = syn::parse_str(s).unwrap();
+
+    assert_eq!(NodeKind::from_name("ExprStruct"), Some(NodeKind::ExprStruct));
+    assert_eq!(NodeKind::from_name("Struct"), None);
+    assert_eq!(NodeKind::ExprCall.to_string(), "ExprCall");
+    assert!(NodeKind::all().contains(&NodeKind::Ident));
+    assert_eq!(syn_query::kind_of(&st), Some(NodeKind::ExprStruct));
+    assert_eq!(syn_query::kind_of(&0u8), None);
+
+    let qr = st.find::<FieldValue>();
+    assert_eq!(qr[0].kind(), NodeKind::FieldValue);
+
+    let kind = NodeKind::from_name("Member").unwrap();
+    let qr = st.find_kind(kind);
+    assert_eq!(qr.len(), 2);
+    assert_eq!(qr[1].kind(), NodeKind::Member);
+    assert_eq!(qr.find::<Ident>()[1].data, "y");
+}
+
+#[test]
+fn query_any() {
+    let s = "fn a() {} impl S { fn b(&self) {} } fn c() {}";
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let qr = file.query_any(&[NodeKind::ItemFn, NodeKind::ImplItemMethod]);
+    assert_eq!(qr.len(), 3);
+    assert_eq!(qr[0].kind(), NodeKind::ItemFn);
+    assert_eq!(qr[1].kind(), NodeKind::ImplItemMethod);
+    assert_eq!(qr[2].kind(), NodeKind::ItemFn);
+
+    let method = qr[1].downcast::<syn::ImplItemMethod>().unwrap();
+    assert_eq!(method.path, qr[1].path);
+    assert_eq!(method.data.sig.ident, "b");
+    assert!(qr[1].downcast::<syn::ItemFn>().is_none());
+    assert!(qr[0].data.downcast_ref::<syn::ItemFn>().is_some());
+
+    let qr = qr.downcast::<syn::ItemFn>();
+    assert_eq!(qr.len(), 2);
+    assert_eq!(qr[1].data.ident, "c");
+
+    let qr = file.query_dyn();
+    assert_eq!(qr[0].kind(), NodeKind::File);
+    assert_eq!(qr.downcast::<Ident>().len(), file.find::<Ident>().len());
+}
+
+#[test]
+fn locations() {
+    let s = "fn main() {\n    let point = Point { x: 1, y: 1 };\n}\n";
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let qr = file.find::<ExprStruct>();
+    let location = qr[0].location.unwrap();
+    assert_eq!((location.start.line, location.start.column), (2, 16));
+    assert_eq!((location.end.line, location.end.column), (2, 36));
+    assert_eq!(location.to_string(), "2:17");
+    assert_eq!(&s[location.byte_range(s).unwrap()], "Point { x: 1, y: 1 }");
+
+    let locations = file.find::<FieldValue>().locations();
+    assert_eq!(locations.len(), 2);
+    let range = locations[1].unwrap().byte_range(s).unwrap();
+    assert_eq!(&s[range], "y: 1");
+
+    let qr = file.find::<syn::Block>();
+    assert_eq!(&s[qr[0].location.unwrap().byte_range(s).unwrap()], &s[10..s.len() - 1]);
+
+    let qr = file.find::<syn::FnDecl>();
+    assert_eq!(&s[qr[0].location.unwrap().byte_range(s).unwrap()], "fn main()");
+
+    let qr = file.find::<syn::Visibility>();
+    assert_eq!(qr[0].location, None);
+}
+
+#[test]
+fn replace_with() {
+    let s = "Point { x: 1, y: 1 }";
+    let st: ExprStruct = syn::parse_str(s).unwrap();
+
+    let st = st.find::<Ident>()
+        .filter(|node| node.data == "y")
+        .replace_with(|node| Ident::new("z", node.data.span()))
+        .unwrap();
+    let qr = st.find::<Ident>();
+    assert_eq!(qr.len(), 3);
+    assert_eq!(qr[2].data, "z");
+
+    let st = st.find::<syn::Expr>()
+        .replace_with(|_| syn::parse_str("2").unwrap())
+        .unwrap();
+    assert_eq!(st, syn::parse_str::<ExprStruct>("Point { x: 2, z: 2 }").unwrap());
+}
+
+#[test]
+fn remove() {
+    let s = "#[derive(Debug)] struct A; fn a() {} fn b() { a(); b(); }";
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let result = file.find::<syn::ItemFn>()
+        .filter(|node| node.data.ident == "a")
+        .remove()
+        .unwrap();
+    assert_eq!(result.items.len(), 2);
+    assert_eq!(result.find::<syn::ItemFn>()[0].data.ident, "b");
+
+    let result = file.find::<syn::Attribute>().remove().unwrap();
+    assert_eq!(result.find::<syn::Attribute>().len(), 0);
+    assert_eq!(result.items.len(), 3);
+
+    let result = file.find::<syn::Stmt>()
+        .not(|node| node.data.find::<Ident>()[0].data == "b")
+        .remove()
+        .unwrap();
+    assert_eq!(result.find::<syn::Stmt>().len(), 1);
+    assert_eq!(result.find::<syn::Stmt>().find::<Ident>()[0].data, "b");
+
+    let idents = file.find::<Ident>();
+    let result = idents.remove();
+    assert_eq!(result, Err(MutateError::NotInContainer(idents[0].path.to_owned())));
+}
+
+#[test]
+fn insert() {
+    let s = "fn f(a: u8, b: u8) { a; }";
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let result = file.find::<syn::ItemFn>()
+        .insert_before(|_| syn::parse_str::<syn::Item>("use std::fmt;").unwrap())
+        .unwrap();
+    assert_eq!(result.items.len(), 2);
+    assert_eq!(result.find::<syn::ItemUse>().len(), 1);
+    assert_eq!(result.find::<syn::ItemUse>()[0].path, vec![0, 0]);
+
+    let result = file.find::<syn::FnArg>()
+        .filter(|node| node.data.find::<Ident>()[0].data == "a")
+        .insert_after(|_| syn::parse_str::<syn::FnArg>("c: u16").unwrap())
+        .unwrap();
+    let idents = result.find::<syn::FnArg>().find::<Ident>().map(|node| node.data);
+    assert_eq!(idents, vec!["a", "u8", "c", "u16", "b", "u8"]);
+
+    let result = file.find::<syn::Stmt>()
+        .insert_after(|_| syn::parse_str::<syn::Stmt>("b;").unwrap())
+        .unwrap();
+    assert_eq!(result.find::<syn::Block>()[0].data.stmts.len(), 2);
+
+    let stmts = file.find::<syn::Stmt>();
+    let result = stmts.insert_after(|_| syn::parse_str::<syn::Item>("fn g() {}").unwrap());
+    assert_eq!(result, Err(MutateError::TypeMismatch(stmts[0].path.to_owned())));
+}
+
+#[test]
+fn borrowed() {
+    let s = "Point { x: 1, y: 1 }";
+    let st: ExprStruct = syn::parse_str(s).unwrap();
+
+    let qr = st.query_ref::<FieldValue>();
+    assert_eq!(qr.len(), 2);
+    assert!(std::ptr::eq(qr[0].data, &st.fields[0]));
+    assert!(std::ptr::eq(qr.root(), &st));
+
+    let qr = qr.children::<syn::Member>().parent::<FieldValue>();
+    assert_eq!(qr.len(), 2);
+    assert_eq!(qr[1].path, vec![3i64]);
+    assert!(std::ptr::eq(qr[1].data, &st.fields[1]));
+
+    let qr = st.find_ref::<Ident>().closest::<FieldValue>();
+    assert_eq!(qr.len(), 2);
+    assert!(std::ptr::eq(qr[0].data, &st.fields[0]));
+
+    let qr = st.query_ref::<FieldValue>().eq(0).unwrap();
+    assert_eq!(qr.path, st.query::<FieldValue>()[0].path);
+    assert_eq!(qr.location(), st.query::<FieldValue>()[0].location);
+
+    let qr = st.find_ref::<FieldValue>().prev_all::<FieldValue>();
+    assert_eq!(qr.len(), 0);
+    let qr = st.find_ref::<FieldValue>().siblings::<FieldValue>();
+    assert_eq!(qr.len(), 2);
+
+    let owned = st.query::<FieldValue>();
+    let qr = owned.as_borrowed().children::<syn::Member>();
+    assert_eq!(qr.len(), 2);
+    assert!(std::ptr::eq(qr.document(), owned.document()));
+    let qr = qr.into_owned().parents::<ExprStruct>();
+    assert_eq!(qr.len(), 1);
+    assert!(std::ptr::eq(qr.document(), owned.document()));
+
+    assert_eq!(st.query_ref::<syn_query::DynNode>().len(), 0);
+}
+
+#[test]
+fn document() {
+    let s = "Point { x: 1, y: 1 }";
+    let st: ExprStruct = syn::parse_str(s).unwrap();
+
+    let qr = st.query::<FieldValue>();
+    let document = qr.document();
+    assert_eq!(document.len(), st.query_dyn().len());
+    assert_eq!(document[0].kind, NodeKind::ExprStruct);
+    assert_eq!(document[0].parent, None);
+
+    let id = document.id(&qr[1].path).unwrap();
+    assert_eq!(document[id].depth, 1);
+    assert_eq!(document[id].parent, Some(0));
+    assert!(document[0].children.contains(&id));
+    assert_eq!(document[document[id].children[0]].kind, NodeKind::Member);
+    assert_eq!(document.path(id), qr[1].path);
+    assert_eq!(document[id].location, qr[1].location);
+    let nodes = st.query_dyn();
+    for (id, node) in nodes.iter().enumerate() {
+        assert_eq!(document.path(id), node.path);
+        assert_eq!(document.id(&node.path), Some(id));
+    }
+    assert_eq!(document.id(&[7]), None);
+
+    let chained = qr.children::<syn::Member>().parent::<FieldValue>();
+    assert_eq!(chained.len(), 2);
+    assert!(std::ptr::eq(chained.document(), document));
+}
+
+#[test]
+fn node_path() {
+    let s = "Point { x: 1, y: 1 }";
+    let st: ExprStruct = syn::parse_str(s).unwrap();
+
+    let qr = st.query::<syn::Member>();
+    let paths: Vec<NodePath> = qr.node_paths().into_iter().map(Option::unwrap).collect();
+    assert_eq!(paths[1].to_string(), "ExprStruct.fields[1].member");
+    assert_eq!(paths[1].steps[0].parent, NodeKind::ExprStruct);
+    assert_eq!(paths[1].steps[1].parent, NodeKind::FieldValue);
+    assert!(paths[0] < paths[1]);
+
+    let path: NodePath = "ExprStruct.fields[1].member".parse().unwrap();
+    assert_eq!(path, paths[1]);
+    assert_eq!(path.kind(), Some(NodeKind::Member));
+    let node = qr.resolve(&path).unwrap();
+    assert_eq!(node.path, qr[1].path);
+
+    let path: NodePath = "ExprStruct.brace_token".parse().unwrap();
+    assert_eq!(qr.resolve(&path).unwrap().kind(), NodeKind::Span);
+    assert!(qr.resolve(&"ExprStruct.fields[2]".parse().unwrap()).is_none());
+
+    let err = "ExprStruct.fields.member".parse::<NodePath>().unwrap_err();
+    assert_eq!(err.column, 18);
+    let err = "ExprStruct.feilds[0]".parse::<NodePath>().unwrap_err();
+    assert_eq!(err.to_string(), "`ExprStruct` has no field `feilds` at column 12");
+    assert!("ExprStruct.path[0]".parse::<NodePath>().is_err());
+}
+
+#[test]
+fn pattern() {
+    let s = "fn f() { let a = x.unwrap(); let b: Option<u8> = y.get(0).unwrap(); z.unwrap_or(1); }";
+    let item: syn::ItemFn = syn::parse_str(s).unwrap();
+
+    let matches = item.matches::<syn::Expr>("$recv.unwrap()").unwrap();
+    assert_eq!(matches.len(), 2);
+    let recv = matches[1].bindings.get::<syn::ExprMethodCall>("recv").unwrap();
+    assert_eq!(recv.data.method, "get");
+    assert_eq!(matches[0].bindings.get::<Ident>("recv").unwrap().data, "x");
+
+    let types = item.query_pattern::<syn::Type>("Option<$T>").unwrap();
+    assert_eq!(types.len(), 1);
+    let matches = item.matches::<syn::Type>("Option<$T>").unwrap();
+    let t = matches[0].bindings.get::<syn::Type>("T").unwrap();
+    assert_eq!(t.kind(), NodeKind::Type);
+    assert!(matches[0].bindings.get::<Ident>("T").unwrap().data == "u8");
+
+    let call: syn::Expr = syn::parse_str("f(1, g(2, 3), 4)").unwrap();
+    let matches = call.matches::<syn::Expr>("$f(2, $..rest)").unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].bindings.get_all::<syn::Expr>("rest").len(), 1);
+    let matches = call.matches::<syn::Expr>("f($..rest, 4)").unwrap();
+    assert_eq!(matches[0].bindings.get_all::<syn::Expr>("rest").len(), 2);
+    assert!(call.matches::<syn::Expr>("f($a, $a, 4)").unwrap().is_empty());
+    assert!(call.matches::<syn::Expr>("f(2, $..rest)").unwrap().is_empty());
+
+    let err = call.matches::<syn::Expr>("f($)").unwrap_err();
+    assert_eq!(err.to_string(), "expected a metavariable name after `$` at column 3");
+
+    let s = "/// hi\nfn a() { foo(2); }\n/// hi\nfn b() { foo(1); }\n#[doc = \" hi\"]\nfn c() { foo(1); }";
+    let file: syn::File = syn::parse_str(s).unwrap();
+    let matches = file
+        .matches::<syn::ItemFn>("/// hi\nfn $name() { foo(1); }")
+        .unwrap();
+    let names: Vec<String> = matches
+        .iter()
+        .map(|m| m.bindings.get::<Ident>("name").unwrap().data.to_string())
+        .collect();
+    assert_eq!(names, vec!["b", "c"]);
+}
+
+#[test]
+fn to_source() {
+    let s = "fn f(a: Vec<u8>) -> usize {\n    let n = a.len();  // length\n    if n > 0 { n } else { 0 }\n}";
+    let item: syn::ItemFn = syn::parse_str(s).unwrap();
+
+    let qr = item.query::<syn::Stmt>();
+    assert_eq!(qr[0].to_source(), "let n = a.len();");
+    assert_eq!(qr.texts()[1], "if n > 0 { n } else { 0 }");
+    assert_eq!(item.query::<syn::Type>()[0].to_source(), "Vec<u8>");
+
+    let pretty = item.query::<syn::Block>().texts_with(Render::Pretty);
+    assert_eq!(
+        pretty[0],
+        "{\n    let n = a.len();\n    if n > 0 {\n        n\n    } else {\n        0\n    }\n}"
+    );
+
+    let source = qr.texts_with(Render::Source(s));
+    assert_eq!(source[1], "if n > 0 { n } else { 0 }");
+    let call = item.query::<syn::ExprMethodCall>();
+    assert_eq!(call[0].render(Render::Source(s)), "a.len()");
+    assert_eq!(item.query_ref::<syn::ExprIf>()[0].to_source(), qr.texts()[1]);
+}
+
+#[test]
+fn set_algebra() {
+    let s = "Point { x: 1, y: 2, z: 3 }";
+    let st: ExprStruct = syn::parse_str(s).unwrap();
+
+    let fields = st.query::<FieldValue>();
+    let first = fields.filter(|node| node.data.member == syn::parse_str("x").unwrap());
+    let last_two = fields.filter(|node| node.path[0] > 2);
+    let ends = last_two
+        .filter(|node| node.path[0] == 4)
+        .union(&first)
+        .unwrap();
+    assert_eq!(ends.len(), 2);
+    assert!(ends[0].path < ends[1].path);
+    assert_eq!(fields.difference(&last_two).unwrap().len(), 1);
+    assert_eq!(last_two.intersection(&ends).unwrap()[0].path, vec![4i64]);
+    assert_eq!(last_two.symmetric_difference(&ends).unwrap().len(), 2);
+    assert_eq!(ends.union(&ends).unwrap().len(), 2);
+
+    let reparsed: ExprStruct = syn::parse_str(s).unwrap();
+    assert_eq!(fields.same_root(&reparsed.query::<FieldValue>()), Ok(()));
+    let other: ExprStruct = syn::parse_str("Point { x: 1 }").unwrap();
+    assert_eq!(
+        fields.union(&other.query::<FieldValue>()).unwrap_err(),
+        RootMismatch::Different
+    );
+
+    let span = Span::call_site();
+    let spans = span.query::<Span>();
+    assert_eq!(spans.same_root(&spans.filter(|_| true)), Ok(()));
+    assert_eq!(
+        spans.union(&span.query::<Span>()).unwrap_err(),
+        RootMismatch::Incomparable
+    );
+
+    let unsorted = syn_query::QueryResult::new(
+        vec![ends[1].clone(), ends[0].clone(), ends[1].clone()],
+        st.clone(),
+    );
+    assert_eq!(unsorted.unique().len(), 2);
+    assert_eq!(unsorted.unique()[0].path, ends[0].path);
+
+    let borrowed = st.query_ref::<FieldValue>();
+    let y = borrowed.filter(|node| node.path[0] == 3);
+    assert_eq!(borrowed.difference(&y).unwrap().len(), 2);
+    assert!(y
+        .union(&other.query_ref::<FieldValue>().filter(|_| false))
+        .is_err());
+}
+
+#[test]
+fn end() {
+    let s = "Point { x: 1, y: Size { w: 2 } }";
+    let st: ExprStruct = syn::parse_str(s).unwrap();
+
+    let paths = st.find::<syn::Path>();
+    let structs = paths.parents::<ExprStruct>();
+    let inner = structs.children::<syn::Path>();
+    assert_eq!(inner.len(), 2);
+    let back = inner.end::<ExprStruct>();
+    assert_eq!(back.len(), structs.len());
+    assert!(std::ptr::eq(&back[0], &structs[0]));
+    assert_eq!(back.end::<syn::Path>().len(), paths.len());
+    assert!(back.end::<syn::Path>().end::<syn::Path>().is_empty());
+
+    let fields = structs.filter(|node| node.path.is_empty()).children::<FieldValue>();
+    let both = fields.add_back();
+    assert_eq!(both.len(), 3);
+    assert_eq!(both[0].kind(), NodeKind::ExprStruct);
+    assert_eq!(both.end::<FieldValue>().len(), 2);
+    assert_eq!(fields.end::<ExprStruct>().end::<ExprStruct>().len(), 2);
+    assert!(fields.end::<syn::Expr>().is_empty());
+}
+
+#[test]
+fn closest() {
+    let s = "mod m { impl S { fn f(&self) { let g = |x| x.unwrap(); a.unwrap(); } } fn h() { b.unwrap() } }";
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let calls = file.query::<syn::ExprMethodCall>();
+    assert_eq!(calls.len(), 3);
+    let methods = calls.closest::<syn::ImplItemMethod>();
+    assert_eq!(methods.len(), 1);
+    assert_eq!(methods[0].data.sig.ident, "f");
+    assert_eq!(calls.closest::<syn::ItemFn>()[0].data.ident, "h");
+    assert_eq!(calls.closest::<syn::ExprMethodCall>().len(), 3);
+    assert_eq!(calls.closest::<syn::ItemMod>().len(), 1);
+    let kinds = [NodeKind::ImplItemMethod, NodeKind::ItemFn];
+    assert_eq!(calls.closest_any(&kinds).len(), 2);
+
+    let scopes = calls.offset_parent();
+    let kinds: Vec<NodeKind> = scopes.iter().map(|node| node.kind()).collect();
+    assert_eq!(kinds, vec![NodeKind::ImplItemMethod, NodeKind::ExprClosure, NodeKind::ItemFn]);
+
+    let blocks = calls.parents_until::<syn::Block, syn::File>();
+    assert_eq!(blocks.len(), 2);
+    let items = calls.parents_until::<syn::Item, syn::ItemMod>();
+    assert_eq!(items.len(), 2);
+    assert!(items.iter().all(|item| !matches!(item.data, syn::Item::Mod(_))));
+    let fns = calls.parents_until::<syn::ItemFn, syn::Block>();
+    assert!(fns.is_empty());
+
+    let borrowed = file.query_ref::<syn::ExprMethodCall>().closest::<syn::ItemFn>();
+    assert_eq!(borrowed.len(), 1);
+    assert_eq!(borrowed[0].data.ident, "h");
+}
+
+#[test]
+fn has_descendant() {
+    let s = "mod m { fn a() { x.unwrap(); } fn b() { y.len(); } fn c() { let unwrap = 1; } }";
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let fns = file.query::<syn::ItemFn>();
+    assert_eq!(fns.has_descendant::<syn::ExprMethodCall>().len(), 2);
+    let unwraps =
+        fns.has_descendant_matching::<syn::ExprMethodCall, _>(|call| call.data.method == "unwrap");
+    assert_eq!(unwraps.len(), 1);
+    assert_eq!(unwraps[0].data.ident, "a");
+    assert!(fns.has_descendant::<syn::ItemFn>().is_empty());
+
+    assert_eq!(fns.contains_ident("unwrap").len(), 2);
+    assert_eq!(fns.contains_ident("c").len(), 1);
+    assert_eq!(fns.contains_text(".unwrap()").len(), 1);
+
+    let borrowed = file.query_ref::<syn::ItemFn>();
+    assert_eq!(borrowed.has_descendant::<syn::Local>()[0].data.ident, "c");
+    assert_eq!(borrowed.contains_ident("y").len(), 1);
+    assert_eq!(borrowed.contains_text("y.len()").len(), 1);
+}
+
+#[test]
+fn query_iter() {
+    let s = "mod m { fn a() { fn b() {} } fn c() {} } fn d() {}";
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let names: Vec<String> = file
+        .query_iter::<syn::ItemFn>()
+        .map(|node| node.data.ident.to_string())
+        .collect();
+    assert_eq!(names, vec!["a", "b", "c", "d"]);
+    let paths: Vec<Vec<i64>> = file.query_iter::<syn::ItemFn>().map(|node| node.path).collect();
+    let eager: Vec<Vec<i64>> = file
+        .query::<syn::ItemFn>()
+        .iter()
+        .map(|node| node.path.to_owned())
+        .collect();
+    assert_eq!(paths, eager);
+    assert_eq!(file.query_iter::<syn::ItemFn>().take(2).count(), 2);
+
+    let fns = file.find::<syn::ItemFn>();
+    assert_eq!(fns.first().unwrap().data.ident, "a");
+    assert!(file.find::<syn::ItemMod>().has());
+    assert!(!file.find::<syn::ItemStruct>().has());
+    assert!(fns.is(|node| node.data.ident == "c"));
+    assert!(!fns.is(|node| node.data.ident == "e"));
+    assert_eq!(fns.eq(2).unwrap().data.ident, "c");
+    assert!(fns.eq(4).is_none());
+    assert_eq!(fns.eq(-1).unwrap().data.ident, "d");
+
+    let inner = fns.find::<syn::ItemFn>();
+    let lazy = (inner.first(), inner.eq(1), inner.eq(4));
+    assert_eq!(inner.len(), 4);
+    assert_eq!(lazy, (Some(inner[0].clone()), Some(inner[1].clone()), None));
+
+    let fns = file.query::<syn::ItemFn>();
+    let nested: Vec<Vec<i64>> = fns.query_iter::<syn::ItemFn>().map(|node| node.path).collect();
+    let eager: Vec<Vec<i64>> = fns
+        .query::<syn::ItemFn>()
+        .iter()
+        .map(|node| node.path.to_owned())
+        .collect();
+    assert_eq!(nested, eager);
+
+    let s = "/// A.\nfn a(x: u8) {\n    //! Inner.\n    let y = [x, 1];\n}";
+    let item: syn::ItemFn = syn::parse_str(s).unwrap();
+    let lazy: Vec<Vec<i64>> = item
+        .query_iter::<syn_query::DynNode>()
+        .map(|node| node.path)
+        .collect();
+    let eager: Vec<Vec<i64>> = item.query_dyn().iter().map(|node| node.path.to_owned()).collect();
+    assert_eq!(lazy, eager);
+    let docs: Vec<Vec<i64>> = item.query_iter::<DocComment>().map(|node| node.path).collect();
+    let eager: Vec<Vec<i64>> = item
+        .query::<DocComment>()
+        .iter()
+        .map(|node| node.path.to_owned())
+        .collect();
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs, eager);
+}
+
+#[test]
+fn attributes() {
+    let s = r#"
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct User {
+    #[serde(rename = "id", default)]
+    user_id: u64,
+    #[serde(skip)]
+    cache: Cache,
+    name: String,
+}
+enum E {
+    #[serde(rename = "a")]
+    A,
+}
+"#;
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let items = file.query::<syn::Item>();
+    assert_eq!(items.with_attribute("serde").len(), 1);
+    assert_eq!(items.with_attribute("derive").len(), 1);
+    assert!(items.with_attribute("repr").is_empty());
+    let fields = file.query::<syn::Field>();
+    assert_eq!(fields.with_attribute("serde").len(), 2);
+
+    let renames = fields.attribute_value("serde", "rename");
+    assert_eq!(renames.len(), 1);
+    assert_eq!(renames.texts(), vec!["\"id\""]);
+    assert_eq!(renames[0].location.unwrap().start.line, 5);
+    let variants = file.query::<syn::Variant>().attribute_value("serde", "rename");
+    assert_eq!(variants.texts(), vec!["\"a\""]);
+    assert_eq!(items.attribute_value("serde", "rename_all").texts(), vec!["\"camelCase\""]);
+
+    let metas = fields.attrs::<syn::Meta>();
+    let names: Vec<String> = metas.iter().map(|meta| meta.data.name().to_string()).collect();
+    assert_eq!(names, vec!["serde", "rename", "default", "serde", "skip"]);
+    assert!(metas[0].path.starts_with(&fields[0].path));
+    assert_eq!(metas[0].path.last(), Some(&syn_query::ATTR_META));
+    assert_eq!(fields.attrs::<syn::NestedMeta>().len(), 3);
+
+    let attributes = fields.query::<syn::Attribute>();
+    let parents = metas.parent::<syn::Attribute>();
+    assert_eq!(parents.len(), 2);
+    assert_eq!((&parents[0], &parents[1]), (&attributes[0], &attributes[1]));
+    assert_eq!(metas.parents::<syn::Field>().len(), 2);
+    assert!(metas.parent::<syn::Meta>().is_empty());
+}
+
+#[test]
+fn doc_comments() {
+    let s = r#"
+//! The crate.
+//! Second line.
+
+/// Adds.
+/// Twice.
+pub fn add() {}
+
+/** A point. */
+pub struct Point {
+    /// The x.
+    pub x: i32,
+    pub y: i32,
+}
+
+#[doc = "Hidden."]
+pub fn hidden() {}
+
+pub fn bare() {}
+fn private() {}
+"#;
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let docs = file.query::<syn::File>().doc_comments();
+    assert_eq!(docs.len(), 1);
+    assert_eq!(docs[0].text, "The crate.\nSecond line.");
+    assert_ne!(docs[0].style, syn::AttrStyle::Outer);
+    assert_eq!(docs[0].owner.kind(), NodeKind::File);
+    assert_eq!(docs[0].attrs.len(), 2);
+    let location = docs[0].location.unwrap();
+    assert_eq!((location.start.line, location.end.line), (2, 3));
+
+    let docs = file.query::<syn::Item>().doc_comments();
+    let texts: Vec<&str> = docs.iter().map(|doc| doc.text.as_str()).collect();
+    assert_eq!(texts, vec!["Adds.\nTwice.", "A point.", "Hidden."]);
+    assert_eq!(docs[0].style, syn::AttrStyle::Outer);
+    assert!(!docs[0].block);
+    assert!(docs[1].block);
+    assert_eq!(docs[1].owner.kind(), NodeKind::Item);
+
+    let fns = file.undocumented::<syn::ItemFn>();
+    assert_eq!(fns.len(), 1);
+    assert_eq!(fns[0].data.ident, "bare");
+    let fields = file.query::<syn::Item>().undocumented::<syn::Field>();
+    assert_eq!(fields.len(), 1);
+    assert_eq!(fields[0].data.ident.as_ref().unwrap(), "y");
+
+    let found = file.find::<DocComment>();
+    let texts: Vec<&str> = found.iter().map(|doc| doc.data.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        vec![
+            "The crate.\nSecond line.",
+            "Adds.\nTwice.",
+            "A point.",
+            "The x.",
+            "Hidden."
+        ]
+    );
+    assert_eq!(found[1].data.owner.kind(), NodeKind::ItemFn);
+    let path = &found[1].path;
+    assert_eq!(path[path.len() - 2], syn_query::DOC_COMMENT);
+    assert_eq!(found[1].location, docs[0].location);
+    assert_eq!(found.parent::<syn::ItemFn>()[0].data.ident, "add");
+    assert_eq!(file.find_kind(NodeKind::DocComment).len(), 5);
+    let fields = file.select("ItemStruct Field > DocComment").unwrap();
+    assert_eq!(fields.len(), 1);
+    assert_eq!(fields[0].kind(), NodeKind::DocComment);
+    assert_eq!(fields[0].to_source(), "#[doc = \" The x.\"]");
+    let attributes = file.find::<syn::Attribute>();
+    assert!(attributes.find::<DocComment>().is_empty());
+
+    let nodes = file.query_dyn();
+    assert!(nodes.iter().all(|node| node.kind() != NodeKind::DocComment));
+    assert_eq!(file.select("*").unwrap().len(), nodes.len());
+    let same = nodes.replace_with(|node| node.data.to_owned()).unwrap();
+    assert_eq!(same, file);
+    let children = file.children::<syn_query::DynNode>();
+    let empty = children.filter(|node| !node.path.is_empty()).remove().unwrap();
+    assert!(empty.attrs.is_empty() && empty.items.is_empty());
+}
+
+#[test]
+fn undocumented_nested() {
+    let s = r#"
+/// Documented.
+pub mod outer {
+    pub fn bare() {}
+    /// Documented.
+    pub fn documented() {}
+    fn private() {}
+    pub struct S {
+        pub field: u8,
+    }
+}
+mod hidden {
+    pub fn unreachable() {}
+}
+/// Documented.
+pub fn body() {
+    pub fn local() {}
+}
+/// Documented.
+pub trait T {
+    fn method();
+}
+"#;
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    let fns = file.undocumented::<syn::ItemFn>();
+    let names: Vec<String> = fns.iter().map(|node| node.data.ident.to_string()).collect();
+    assert_eq!(names, vec!["bare"]);
+    assert_eq!(file.undocumented::<syn::Field>().len(), 1);
+    assert_eq!(file.undocumented::<syn::ItemStruct>().len(), 1);
+    let methods = file.undocumented::<syn::TraitItemMethod>();
+    assert_eq!(methods.len(), 1);
+    assert_eq!(file.undocumented::<syn::ItemMod>().len(), 0);
+}
+
+#[test]
+fn query_macros() {
+    let s = r#"
+fn f() {
+    let v = vec![g(1), g(2)];
+    println!("{}", h(vec![0; n()]));
+    thread_local! { static K: u8 = k(); }
+    my_dsl!(=> unparsable <=);
+}
+"#;
+    let file: syn::File = syn::parse_str(s).unwrap();
+
+    assert!(file.query::<syn::ExprCall>().is_empty());
+    let calls = file.query_macros::<syn::ExprCall>();
+    let names: Vec<String> = calls.iter().map(|call| call.to_source()).collect();
+    assert_eq!(names, vec!["g(1)", "g(2)", "h(vec![0; n()])", "n()", "k()"]);
+    assert_eq!(calls[0].location.unwrap().start.line, 3);
+    assert!(calls.iter().all(|call| call.path.contains(&syn_query::MACRO_BODY)));
+    let mac = file.query::<syn::Macro>();
+    assert!(calls[0].path.starts_with(&mac[0].path));
+    let body = &calls[0].path[mac[0].path.len()..];
+    assert_eq!(body[..2], [syn_query::MACRO_BODY, 0]);
+
+    assert_eq!(file.query_macros::<syn::ItemStatic>().len(), 1);
+    let stmts = file.query::<syn::Stmt>();
+    assert_eq!(stmts.query_macros::<syn::ExprCall>().len(), 5);
+    assert_eq!(file.query_macros::<syn::Macro>().len(), 5);
+}
+
+#[test]
+fn tokens() {
+    use proc_macro2::{Delimiter, Group, Literal, Punct, TokenStream, TokenTree};
+    use syn_query::TokenQueryable;
+
+    let stream: TokenStream = "a => { b::c(1, \"x\") } + => ; == =".parse().unwrap();
+
+    let groups = stream.query::<Group>();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].data.delimiter(), Delimiter::Brace);
+    assert_eq!(groups[0].path, vec![3]);
+    assert_eq!(groups[1].path, vec![3, 4]);
+    assert_eq!(groups.texts()[1], "(1, \"x\")");
+
+    let literals = stream.query::<Literal>();
+    assert_eq!(literals.texts(), vec!["1", "\"x\""]);
+    assert_eq!(literals[1].location.unwrap().start.column, 15);
+    assert!(stream.children::<Literal>().is_empty());
+    assert_eq!(groups.children::<Literal>().len(), 2);
+    assert_eq!(groups.query::<Literal>().len(), 2);
+    assert_eq!(groups.query::<Group>().len(), 2);
+    assert_eq!(groups.filter(|node| node.path.len() == 1).query::<Group>().len(), 2);
+    assert_eq!(literals.query::<TokenTree>().texts(), literals.texts());
+    assert_eq!(literals.parent::<Group>().texts(), vec!["(1, \"x\")"]);
+
+    let root = stream.children::<TokenTree>();
+    assert_eq!(root.len(), 11);
+    let arrows = stream.query::<Punct>().ops("=>");
+    let paths: Vec<Vec<i64>> = arrows.iter().map(|node| node.path.to_owned()).collect();
+    assert_eq!(paths, vec![vec![1], vec![5]]);
+    assert_eq!(groups.query::<Punct>().ops("::").len(), 1);
+    assert_eq!(stream.children::<TokenTree>().ops("=").len(), 1);
+
+    let ident = stream.children::<proc_macro2::Ident>();
+    assert_eq!(ident.texts(), vec!["a"]);
+    assert_eq!(ident.next::<Punct>().texts(), vec!["="]);
+    assert!(ident.prev::<TokenTree>().is_empty());
+    assert_eq!(ident.next_all::<Group>().len(), 1);
+    assert_eq!(groups.prev::<Punct>().texts(), vec![">"]);
+    assert_eq!(literals.siblings::<Punct>().texts(), vec![","]);
+    assert_eq!(literals.prev_all::<TokenTree>().texts(), vec!["1", ","]);
+    assert_eq!(stream.find::<Literal>().len(), 2);
+}
+
+#[test]
+fn definitions() {
+    let s = r#"
+const LIMIT: u32 = 10;
+fn f(x: u32, v: Option<u32>) -> u32 {
+    let y = x + 1;
+    let x = y * 2;
+    let add = |a: u32| a + x;
+    match v {
+        Some(x) => add(x),
+        None => g(LIMIT),
+    }
+}
+fn g(x: u32) -> u32 {
+    fn inner() -> u32 { LIMIT }
+    if let Some(z) = Some(x) { z } else { x }
+}
+"#;
+    let file: syn::File = syn::parse_str(s).unwrap();
+    let idents = file.query::<Ident>();
+    let at = |line: usize, column: usize| {
+        idents
+            .iter()
+            .find(|ident| {
+                let start = ident.location.unwrap().start;
+                (start.line, start.column) == (line, column)
+            })
+            .cloned()
+            .unwrap_or_else(|| panic!("{}:{}", line, column))
+    };
+    let line_of = |ident: &syn_query::Node<Ident>| ident.location.unwrap().start.line;
+
+    let param = at(3, 5);
+    assert_eq!(param.data, "x");
+    assert_eq!(idents.definition_of(&param).unwrap().path, param.path);
+    let uses: Vec<usize> = idents.references_of(&param).iter().map(line_of).collect();
+    assert_eq!(uses, vec![4]);
+
+    let shadow = idents.definition_of(&at(6, 27)).unwrap();
+    assert_eq!((line_of(&shadow), shadow.data.to_string()), (5, "x".to_owned()));
+    let arm = idents.definition_of(&at(8, 23)).unwrap();
+    assert_eq!(line_of(&arm), 8);
+    assert_eq!(line_of(&idents.definition_of(&at(8, 19)).unwrap()), 6);
+
+    let limit = at(2, 6);
+    let uses: Vec<usize> = idents.references_of(&limit).iter().map(line_of).collect();
+    assert_eq!(uses, vec![9, 13]);
+    let g = idents.references_of(&at(12, 3));
+    assert_eq!(g.iter().map(line_of).collect::<Vec<_>>(), vec![9]);
+
+    let z = idents.references_of(&at(14, 16));
+    assert_eq!(z.len(), 1);
+    let x = idents.references_of(&at(12, 5));
+    assert_eq!(x.iter().map(line_of).collect::<Vec<_>>(), vec![14, 14]);
+    assert!(idents.definition_of(&at(8, 8)).is_none());
+}
+
+#[test]
+fn load_crate() {
+    use std::path::{Path, PathBuf};
+    use syn_query::Crate;
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/krate");
+    let krate = Crate::load(&dir).unwrap();
+    let src = dir.join("src");
+    let files: Vec<String> = krate
+        .files()
+        .iter()
+        .map(|file| file.strip_prefix(&src).unwrap().display().to_string())
+        .collect();
+    let expected = [
+        "lib.rs",
+        "a.rs",
+        "a/b.rs",
+        "c/mod.rs",
+        "other/d.rs",
+        "inline/e.rs",
+        "inline/f.rs",
+    ];
+    assert_eq!(files, expected);
+    assert_eq!(krate.errors().len(), 1);
+    assert_eq!(krate.errors()[0].message, "file not found for module `missing`");
+
+    let fns = krate.query::<syn::ItemFn>();
+    let located: Vec<String> = fns
+        .iter()
+        .map(|node| {
+            let location = krate.locate(node).unwrap();
+            let file = location.file.strip_prefix(&src).unwrap().display();
+            format!("{}:{} {}", file, location.location, node.data.ident)
+        })
+        .collect();
+    let expected = [
+        "a/b.rs:1:1 in_b",
+        "a.rs:5:1 in_a",
+        "c/mod.rs:1:1 in_c",
+        "other/d.rs:1:1 in_d",
+        "inline/e.rs:1:1 in_e",
+        "inline/f.rs:1:1 in_f",
+        "lib.rs:15:1 root",
+    ];
+    assert_eq!(located, expected);
+    assert_eq!(krate.text_of(&fns[1]).unwrap(), "pub fn in_a() {}");
+
+    let modules = krate.query::<syn::ItemMod>();
+    assert_eq!(krate.file_of(&modules[0]).unwrap(), src.join("lib.rs"));
+    assert_eq!(modules[0].location.unwrap().end.line, 3);
+    let a = modules.filter(|node| node.data.ident == "a");
+    let inner = a.children::<syn::Attribute>();
+    assert_eq!(inner.len(), 1);
+    let location = krate.locate(&inner[0]).unwrap();
+    assert_eq!(location.file, src.join("a.rs"));
+    assert_eq!(location.location.start.line, 1);
+    assert_eq!(krate.text_of(&inner[0]).unwrap(), "//! Module a.");
+    let docs = a.children::<DocComment>();
+    assert_eq!(docs[0].data.text, "Module a.");
+    assert_eq!(krate.file_of(&docs[0]).unwrap(), src.join("a.rs"));
+    let root = krate.query::<syn::File>().children::<syn::Attribute>();
+    assert_eq!(krate.file_of(&root[0]).unwrap(), src.join("lib.rs"));
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/cycle");
+    let krate = Crate::load(&dir).unwrap();
+    assert_eq!(krate.files().len(), 2);
+    let src = dir.join("src");
+    let lib = src.join("lib.rs");
+    let again = |name: &str| {
+        format!(
+            "module `{}` includes `{}`, which is already being read",
+            name,
+            lib.display()
+        )
+    };
+    let errors: Vec<(PathBuf, String)> = krate
+        .errors()
+        .iter()
+        .map(|error| (error.file.to_owned(), error.message.to_owned()))
+        .collect();
+    let expected = vec![
+        (src.join("a.rs"), again("back")),
+        (lib.to_owned(), again("again")),
+    ];
+    assert_eq!(errors, expected);
+    assert_eq!(krate.query::<syn::ItemFn>().len(), 2);
+}
+
+#[test]
+fn lints() {
+    use syn_query::{DynNode, Lint, LintRunner, Node, Severity};
+
+    struct Unwrap;
+    impl Lint for Unwrap {
+        fn name(&self) -> &'static str {
+            "no_unwrap"
+        }
+        fn query(&self) -> Vec<NodeKind> {
+            vec![NodeKind::ExprMethodCall]
+        }
+        fn message(&self, node: &Node<DynNode>) -> Option<String> {
+            let call = node.data.downcast_ref::<syn::ExprMethodCall>()?;
+            if call.method == "unwrap" {
+                Some("called `unwrap`".to_owned())
+            } else {
+                None
+            }
+        }
+    }
+    struct Shouting;
+    impl Lint for Shouting {
+        fn name(&self) -> &'static str {
+            "shouting"
+        }
+        fn severity(&self) -> Severity {
+            Severity::Error
+        }
+        fn query(&self) -> Vec<NodeKind> {
+            vec![NodeKind::ItemFn]
+        }
+        fn message(&self, node: &Node<DynNode>) -> Option<String> {
+            let item = node.data.downcast_ref::<syn::ItemFn>()?;
+            let name = item.ident.to_string();
+            if name == name.to_uppercase() {
+                Some(format!("function `{}` is all caps", name))
+            } else {
+                None
+            }
+        }
+    }
+
+    let s = "fn a() {
+    x.unwrap();
+}
+#[allow(no_unwrap)]
+fn B() {
+    y.unwrap();
+}
+mod m {
+    #![allow(shouting)]
+    fn C() { z.unwrap(); }
+}
+";
+    let file: syn::File = syn::parse_str(s).unwrap();
+    let mut runner = LintRunner::new();
+    runner.add(Unwrap).add(Shouting);
+    let diagnostics = runner.run(&file);
+    let found: Vec<(&str, usize)> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.lint, diagnostic.span.unwrap().start.line))
+        .collect();
+    assert_eq!(found, vec![("no_unwrap", 2), ("shouting", 4), ("no_unwrap", 10)]);
+    assert_eq!(diagnostics[1].severity, Severity::Error);
+
+    let report = diagnostics[0].to_rustc("src/a.rs", s);
+    let expected = "warning[no_unwrap]: called `unwrap`
+ --> src/a.rs:2:5
+  |
+2 |     x.unwrap();
+  |     ^^^^^^^^^^
+";
+    assert_eq!(report, expected);
+    let json = diagnostics[1].to_json("src/a.rs");
+    assert!(json.starts_with(
+        "{\"lint\":\"shouting\",\"severity\":\"error\",\"message\":\"function `B` is all caps\",\
+         \"file\":\"src/a.rs\",\"line\":4,\"column\":1,\"end_line\":7,\"end_column\":2,\"path\":["
+    ));
+
+    struct Styled;
+    impl Lint for Styled {
+        fn name(&self) -> &'static str {
+            "style::shouting"
+        }
+        fn query(&self) -> Vec<NodeKind> {
+            Shouting.query()
+        }
+        fn message(&self, node: &Node<DynNode>) -> Option<String> {
+            Shouting.message(node)
+        }
+    }
+    let s = "#[allow(unused, style::shouting)]\nfn D() {}\nfn E() {\n\tf(\"\u{e9}\", y.unwrap());\n}\n";
+    let file: syn::File = syn::parse_str(s).unwrap();
+    let mut runner = LintRunner::new();
+    runner.add(Unwrap).add(Styled);
+    let diagnostics = runner.run(&file);
+    let found: Vec<(&str, usize)> = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.lint, diagnostic.span.unwrap().start.line))
+        .collect();
+    assert_eq!(found, vec![("style::shouting", 3), ("no_unwrap", 4)]);
+    let report = diagnostics[1].to_rustc("src/a.rs", s);
+    let expected = "warning[no_unwrap]: called `unwrap`
+ --> src/a.rs:4:10
+  |
+4 | \tf(\"\u{e9}\", y.unwrap());
+  | \t       ^^^^^^^^^^
+";
+    assert_eq!(report, expected);
+
+    struct Kind(&'static str, NodeKind);
+    impl Lint for Kind {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+        fn query(&self) -> Vec<NodeKind> {
+            vec![self.1]
+        }
+        fn message(&self, node: &Node<DynNode>) -> Option<String> {
+            Some(format!("a `{}`", node.kind()))
+        }
+    }
+    let file: syn::File = syn::parse_str("#[allow(no_files, no_items)]\nfn a() {}").unwrap();
+    let mut runner = LintRunner::new();
+    runner
+        .add(Kind("no_files", NodeKind::File))
+        .add(Kind("no_items", NodeKind::Item));
+    let lints: Vec<&str> = runner
+        .run(&file)
+        .iter()
+        .map(|diagnostic| diagnostic.lint)
+        .collect();
+    assert_eq!(lints, vec!["no_files"]);
+}
+
+#[test]
+fn compile_errors() {
+    use proc_macro2::TokenTree;
+    use syn_query::Errors;
+
+    let s = "struct S {\n    a: u8,\n    #[skip]\n    b: Vec<u8>,\n}";
+    let item: syn::ItemStruct = syn::parse_str(s).unwrap();
+    let fields = item.query::<syn::Field>();
+
+    let tokens = fields[0].error("unsupported field");
+    assert_eq!(tokens.to_string(), "compile_error ! { \"unsupported field\" }");
+    let trees: Vec<TokenTree> = tokens.into_iter().collect();
+    assert_eq!(trees[0].span().start().line, 2);
+    assert_eq!(trees[2].span().end().line, 2);
+    assert_eq!(trees[2].span().end().column, 9);
+
+    let all = fields.error("bad");
+    assert_eq!(all.into_iter().count(), 6);
+
+    let mut errors = Errors::new();
+    assert!(errors.clone().finish().is_ok());
+    errors
+        .extend(&fields.with_attribute("skip"), "skipped")
+        .push(&fields[0], "first")
+        .check(Err(item.query::<syn::Ident>()[0].error("name")))
+        .check(Ok::<(), proc_macro2::TokenStream>(()));
+    assert_eq!(errors.len(), 3);
+    let output = errors.finish().unwrap_err();
+    let text = output.to_string();
+    assert_eq!(text.matches("compile_error").count(), 3);
+    let spans: Vec<usize> = output
+        .into_iter()
+        .step_by(3)
+        .map(|tree| tree.span().start().line)
+        .collect();
+    assert_eq!(spans, vec![3, 2, 1]);
+}
+
+#[test]
+fn rewrite() {
+    use syn_query::{RewriteError, Rewriter};
+
+    let s = "fn main() {\n    // keep me\n    let a = old(1);   /* and me */\n    drop(a);\n}\n";
+    let file = syn::parse_file(s).unwrap();
+    let calls = file.query::<syn::ExprCall>();
+    let stmts = file.query::<syn::Stmt>();
+
+    let mut rewriter = Rewriter::new(s);
+    assert_eq!(rewriter.text(&calls[0]), Some("old(1)"));
+    let output = rewriter
+        .replace(&calls[0], "new(1, 2)")
+        .delete(&stmts[1])
+        .insert_before(&stmts[0], "// before\n    ")
+        .insert_after(&stmts[0], " // after")
+        .finish()
+        .unwrap();
+    assert_eq!(
+        output,
+        "fn main() {\n    // keep me\n    // before\n    let a = new(1, 2); // after   /* and me */\n    \n}\n"
+    );
+    assert_eq!(Rewriter::new(s).finish().unwrap(), s);
+
+    let idents = file.query::<syn::Ident>();
+    let error = Rewriter::new(s)
+        .replace(&stmts[0], "let b = 0;")
+        .replace(&idents[1], "b")
+        .finish()
+        .unwrap_err();
+    assert_eq!(error, RewriteError::Overlap(31..46, 35..36));
+    let error = Rewriter::new(s).replace_range(3..200, "").finish();
+    assert_eq!(error, Err(RewriteError::OutOfBounds(3..200)));
+
+    let s = "\u{feff}fn f() { old(1); }\n";
+    let file = syn::parse_file(s).unwrap();
+    let calls = file.query::<syn::ExprCall>();
+    assert_eq!(calls[0].render(Render::Source(s)), "old(1)");
+    let output = Rewriter::new(s).replace(&calls[0], "new()").finish();
+    assert_eq!(output.unwrap(), "\u{feff}fn f() { new(); }\n");
+}